@@ -1,11 +1,13 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
+#![allow(deprecated)]
 
 #[macro_use]
 extern crate criterion;
 
+use bintext::Engine;
 use core::time::Duration;
-use criterion::{black_box, BatchSize, Criterion, ParameterizedBenchmark, Throughput};
+use criterion::{black_box, BatchSize, Bencher, Criterion, ParameterizedBenchmark, Throughput};
 use rand::prelude::*;
 use std::fmt;
 
@@ -22,6 +24,35 @@ impl fmt::Debug for Bytes {
     }
 }
 
+/// Decoding benchmark of any [`Engine`], the text is encoded by the engine itself
+/// and the decoded bytes are checked against the `reference` decoder
+fn engine_decode<E: Engine + 'static>(
+    engine: E,
+    reference: fn(&str) -> Vec<u8>,
+) -> impl FnMut(&mut Bencher, &Bytes) {
+    move |b, data| {
+        let text = engine.encode(&data.0);
+        assert_eq!(engine.decode(&text).unwrap(), reference(&text));
+        assert_eq!(reference(&text), data.0);
+        b.iter_batched(
+            || &text,
+            |value| black_box(engine.decode(value).unwrap()),
+            BatchSize::NumIterations(LEN as u64),
+        )
+    }
+}
+
+/// Encoding benchmark of any [`Engine`]
+fn engine_encode<E: Engine + 'static>(engine: E) -> impl FnMut(&mut Bencher, &Bytes) {
+    move |b, data| {
+        b.iter_batched(
+            || &data.0,
+            |value| black_box(engine.encode(&value[..])),
+            BatchSize::NumIterations(LEN as u64),
+        )
+    }
+}
+
 fn cmp(c: &mut Criterion) {
     let core_ids = core_affinity::get_core_ids().unwrap();
     core_affinity::set_for_current(core_ids[0]);
@@ -55,6 +86,12 @@ fn cmp(c: &mut Criterion) {
                 BatchSize::NumIterations(LEN as u64),
             )
         })
+        .with_function(
+            "bintext",
+            engine_decode(bintext::base64::URL_SAFE, |text| {
+                base64::decode_config(text, base64::URL_SAFE).unwrap()
+            }),
+        )
         .throughput(|d| Throughput::Bytes(d.0.len() as u64))
         .warm_up_time(WARM_UP_TIME)
         .measurement_time(MEASUREMENT_TIME),
@@ -80,6 +117,7 @@ fn cmp(c: &mut Criterion) {
                 BatchSize::NumIterations(LEN as u64),
             )
         })
+        .with_function("bintext", engine_encode(bintext::base64::URL_SAFE))
         .throughput(|d| Throughput::Bytes(d.0.len() as u64))
         .warm_up_time(WARM_UP_TIME)
         .measurement_time(MEASUREMENT_TIME),
@@ -87,6 +125,10 @@ fn cmp(c: &mut Criterion) {
 
     ///////////////////////////////////////////////////////////////////////////////
 
+    let swar = bintext::hex::Hex::new()
+        .backend(bintext::hex::Backend::Swar)
+        .unwrap();
+
     c.bench(
         "decode",
         ParameterizedBenchmark::new(
@@ -112,8 +154,7 @@ fn cmp(c: &mut Criterion) {
                 || hex::encode(&data.0[..]),
                 |value| {
                     let l = value.len() >> 1;
-                    let mut v = Vec::with_capacity(l);
-                    v.resize(l, 0);
+                    let mut v = vec![0; l];
                     black_box((
                         faster_hex::hex_decode(value.as_bytes(), &mut v[..]).unwrap(),
                         v,
@@ -122,18 +163,14 @@ fn cmp(c: &mut Criterion) {
                 BatchSize::NumIterations(LEN as u64),
             )
         })
-        .with_function("bintext", |b, data| {
-            let hex = hex::encode(&data.0);
-            assert_eq!(
-                hex::decode(&hex).unwrap(),
-                bintext::hex::decode_noerr(&hex).unwrap()
-            );
-            b.iter_batched(
-                || &hex,
-                |value| black_box(bintext::hex::decode_noerr(value).unwrap()),
-                BatchSize::NumIterations(LEN as u64),
-            )
-        })
+        .with_function(
+            "bintext",
+            engine_decode(bintext::hex::Hex::new(), |text| hex::decode(text).unwrap()),
+        )
+        .with_function(
+            "bintext-swar",
+            engine_decode(swar, |text| hex::decode(text).unwrap()),
+        )
         .throughput(|d| Throughput::Bytes(d.0.len() as u64))
         .warm_up_time(WARM_UP_TIME)
         .measurement_time(MEASUREMENT_TIME),
@@ -164,21 +201,14 @@ fn cmp(c: &mut Criterion) {
                 || &data.0,
                 |value| {
                     let l = value.len() << 1;
-                    let mut v = Vec::with_capacity(l);
-                    v.resize(l, 0);
+                    let mut v = vec![0; l];
                     black_box((faster_hex::hex_encode(value, &mut v[..]).unwrap(), v))
                 },
                 BatchSize::NumIterations(LEN as u64),
             )
         })
-        .with_function("bintext", |b, data| {
-            assert_eq!(hex::encode(&data.0), bintext::hex::encode(&data.0[..]));
-            b.iter_batched(
-                || &data.0,
-                |value| black_box(bintext::hex::encode(&value[..])),
-                BatchSize::NumIterations(LEN as u64),
-            )
-        })
+        .with_function("bintext", engine_encode(bintext::hex::Hex::new()))
+        .with_function("bintext-swar", engine_encode(swar))
         .throughput(|d| Throughput::Bytes(d.0.len() as u64))
         .warm_up_time(WARM_UP_TIME)
         .measurement_time(MEASUREMENT_TIME),
@@ -0,0 +1,766 @@
+//! Base64 encoding and decoding, supports the standard and URL safe
+//! alphabets with or without padding
+
+// SIMD tables are only used by the x86 backends
+#![cfg_attr(
+    not(any(target_arch = "x86", target_arch = "x86_64")),
+    allow(dead_code)
+)]
+
+use core::error::Error;
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::{Backend, Engine, UnsupportedBackend};
+
+use dispatch::Functions;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2;
+mod dispatch;
+mod fallback;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2;
+
+mod tests;
+
+/// Invalid symbol
+const I: u8 = 255;
+
+/// Symbols used by the encoder and the LUTs used to decode them
+struct Alphabet {
+    name: &'static str,
+    encode: [u8; 64],
+    decode: [u8; 256],
+    /// Offsets added to the 6 bits indexes to get their ASCII symbols,
+    /// indexed by the class of each 6 bits index (see the SIMD `encode`)
+    encode_shift: [u8; 16],
+    /// Bitsets indexed by the lower nibble of each char, a char is invalid
+    /// when the bitset of both of its nibbles have a common bit
+    decode_lo: [u8; 16],
+    /// Bitsets indexed by the higher nibble of each char
+    decode_hi: [u8; 16],
+    /// Offsets added to the chars to get their 6 bits values, indexed by the
+    /// higher nibble of each char
+    decode_roll: [u8; 16],
+    /// Char that shares its higher nibble with chars with another offset
+    decode_special: u8,
+    /// Added to the higher nibble of the special char to pick its offset
+    decode_special_delta: u8,
+}
+
+const fn decode_table(encode: &[u8; 64]) -> [u8; 256] {
+    let mut table = [I; 256];
+    let mut i = 0;
+    while i < 64 {
+        table[encode[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+const fn encode_shift(c62: u8, c63: u8) -> [u8; 16] {
+    let mut shift = [0u8; 16];
+    let mut i = 1;
+    while i < 11 {
+        shift[i] = b'0'.wrapping_sub(52);
+        i += 1;
+    }
+    shift[0] = b'a'.wrapping_sub(26);
+    shift[11] = c62.wrapping_sub(62);
+    shift[12] = c63.wrapping_sub(63);
+    shift[13] = b'A';
+    shift
+}
+
+const STANDARD_ENCODE: [u8; 64] =
+    *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+static STANDARD_ALPHABET: Alphabet = Alphabet {
+    name: "standard",
+    encode: STANDARD_ENCODE,
+    decode: decode_table(&STANDARD_ENCODE),
+    encode_shift: encode_shift(b'+', b'/'),
+    #[rustfmt::skip]
+    decode_lo: [
+        0x15, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x13, 0x1a, 0x1b, 0x1b, 0x1b, 0x1a,
+    ],
+    #[rustfmt::skip]
+    decode_hi: [
+        0x10, 0x10, 0x01, 0x02, 0x04, 0x08, 0x04, 0x08,
+        0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10,
+    ],
+    #[rustfmt::skip]
+    decode_roll: [
+        0, 16, 19, 4, (-65i8) as u8, (-65i8) as u8, (-71i8) as u8, (-71i8) as u8,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ],
+    // '/' is picked by the index `0x2 - 1`
+    decode_special: b'/',
+    decode_special_delta: (-1i8) as u8,
+};
+
+const URL_SAFE_ENCODE: [u8; 64] =
+    *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+static URL_SAFE_ALPHABET: Alphabet = Alphabet {
+    name: "url_safe",
+    encode: URL_SAFE_ENCODE,
+    decode: decode_table(&URL_SAFE_ENCODE),
+    encode_shift: encode_shift(b'-', b'_'),
+    #[rustfmt::skip]
+    decode_lo: [
+        0x15, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x13, 0x3b, 0x3b, 0x3a, 0x3b, 0x33,
+    ],
+    #[rustfmt::skip]
+    decode_hi: [
+        0x10, 0x10, 0x01, 0x02, 0x04, 0x08, 0x04, 0x20,
+        0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10,
+    ],
+    #[rustfmt::skip]
+    decode_roll: [
+        0, 0, 17, 4, (-65i8) as u8, (-65i8) as u8, (-71i8) as u8, (-71i8) as u8,
+        0, 0, 0, 0, 0, 0, (-32i8) as u8, 0,
+    ],
+    // '_' is picked by the index `0x5 + 9`
+    decode_special: b'_',
+    decode_special_delta: 9,
+};
+
+/// Base64 alphabet and padding configuration, encodes and decodes with the
+/// [current](Backend::current) backend unless configured otherwise
+#[derive(Clone, Copy)]
+pub struct Config {
+    alphabet: &'static Alphabet,
+    pad: bool,
+    /// The selected backend is used when `None`
+    functions: Option<&'static Functions>,
+}
+
+/// Standard alphabet (RFC 4648) with padding
+pub const STANDARD: Config = Config {
+    alphabet: &STANDARD_ALPHABET,
+    pad: true,
+    functions: None,
+};
+
+/// Standard alphabet (RFC 4648) without padding
+pub const STANDARD_NO_PAD: Config = Config {
+    alphabet: &STANDARD_ALPHABET,
+    pad: false,
+    functions: None,
+};
+
+/// URL safe alphabet (RFC 4648) with padding
+pub const URL_SAFE: Config = Config {
+    alphabet: &URL_SAFE_ALPHABET,
+    pad: true,
+    functions: None,
+};
+
+/// URL safe alphabet (RFC 4648) without padding
+pub const URL_SAFE_NO_PAD: Config = Config {
+    alphabet: &URL_SAFE_ALPHABET,
+    pad: false,
+    functions: None,
+};
+
+impl Config {
+    /// Same alphabet with or without padding
+    pub const fn pad(self, pad: bool) -> Self {
+        Config { pad, ..self }
+    }
+
+    pub const fn is_padded(&self) -> bool {
+        self.pad
+    }
+
+    /// Uses `backend` instead of the [current](Backend::current) one, only
+    /// the fallback, SSSE3 and AVX2 backends are implemented by base64
+    ///
+    /// ```rust
+    /// use bintext::{base64, Backend};
+    ///
+    /// let config = base64::STANDARD.backend(Backend::Fallback).unwrap();
+    /// let mut buffer = [0; 16];
+    /// let text = base64::encode_noalloc(b"\xa1\xf7", &mut buffer, config).unwrap();
+    /// assert_eq!(text, "ofc=");
+    ///
+    /// assert!(base64::STANDARD.backend(Backend::Swar).is_err());
+    /// ```
+    pub fn backend(self, backend: Backend) -> Result<Self, UnsupportedBackend> {
+        match dispatch::table(backend) {
+            Some(functions) if backend.is_supported() => Ok(Config {
+                functions: Some(functions),
+                ..self
+            }),
+            _ => Err(UnsupportedBackend(backend)),
+        }
+    }
+
+    #[inline(always)]
+    fn functions(&self) -> &'static Functions {
+        match self.functions {
+            Some(functions) => functions,
+            None => dispatch::functions(),
+        }
+    }
+
+    /// Decodes `n` bytes from the chars at `src` into `dst`
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for reads of the chars of the `n` bytes and `dst`
+    /// for `n` writes, they can only overlap when `dst` isn't after `src`
+    #[inline]
+    unsafe fn decode_raw(&self, src: *const u8, dst: *mut u8, n: usize) -> Result<(), DecodeError> {
+        (self.functions().decode_raw)(src, dst, n, *self)
+    }
+
+    /// Encodes the `len` bytes at `src` into `dst`
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for `len` reads and `dst` for writes of the encoded
+    /// length, without overlapping
+    #[inline]
+    unsafe fn encode_raw(&self, src: *const u8, len: usize, dst: *mut u8) {
+        (self.functions().encode_raw)(src, len, dst, *self)
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("alphabet", &self.alphabet.name)
+            .field("pad", &self.pad)
+            .field(
+                "backend",
+                &self.functions.map(|functions| functions.backend),
+            )
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Input length can't be produced by the encoder
+    InvalidLength,
+    /// Invalid char, misplaced padding or last char with unused bits set
+    InvalidCharAt(usize),
+    /// Offset was less than alignment (it needs to be at least equal or greater)
+    BadOffset,
+    /// Offset is after the end of the input
+    OffsetPastEnd { offset: usize, len: usize },
+    /// Alignment isn't a power of two
+    InvalidAlignment(usize),
+    /// Output buffer can't hold all the decoded bytes
+    OutputTooSmall { needed: usize, got: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DecodeError::*;
+        match self {
+            InvalidLength => write!(f, "invalid length, base64 string was truncated"),
+            InvalidCharAt(pos) => write!(f, "invalid base64 char at {}", pos),
+            BadOffset => write!(
+                f,
+                "not enough offset was given, it needs to be equal or greater than alignment"
+            ),
+            OffsetPastEnd { offset, len } => write!(
+                f,
+                "offset {} is past the end of the input of length {}",
+                offset, len
+            ),
+            InvalidAlignment(align) => write!(f, "alignment {} isn't a power of two", align),
+            OutputTooSmall { needed, got } => write!(
+                f,
+                "output buffer too small, needed {} bytes but got {}",
+                needed, got
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// Output buffer can't hold all the encoded chars
+    OutputTooSmall { needed: usize, got: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EncodeError::*;
+        match self {
+            OutputTooSmall { needed, got } => write!(
+                f,
+                "output buffer too small, needed {} bytes but got {}",
+                needed, got
+            ),
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+/// Number of bytes decoded from `input`, only the length and the padding are checked
+#[inline(always)]
+fn decoded_len(input: &[u8], pad: bool) -> Result<usize, DecodeError> {
+    use DecodeError::*;
+
+    let mut c = input.len();
+    if pad {
+        if c & 3 != 0 {
+            Err(InvalidLength)?
+        }
+
+        // Up to two padding chars
+        if c > 0 && input[c - 1] == b'=' {
+            c -= 1;
+            if input[c - 1] == b'=' {
+                c -= 1;
+            }
+        }
+    }
+
+    match c & 3 {
+        0 => Ok((c >> 2) * 3),
+        1 => Err(InvalidLength),
+        r => Ok((c >> 2) * 3 + r - 1),
+    }
+}
+
+/// Checks if `output` is big enough to hold the decoded `input`,
+/// returns the number of bytes that will be decoded
+#[inline(always)]
+fn decoded_len_checked(input: &[u8], output: &[u8], pad: bool) -> Result<usize, DecodeError> {
+    let needed = decoded_len(input, pad)?;
+    if output.len() < needed {
+        Err(DecodeError::OutputTooSmall {
+            needed,
+            got: output.len(),
+        })?
+    }
+
+    Ok(needed)
+}
+
+/// Number of chars needed to encode `len` bytes
+#[inline(always)]
+fn encoded_len(len: usize, pad: bool) -> usize {
+    let r = len % 3;
+    if r == 0 {
+        (len / 3) << 2
+    } else if pad {
+        ((len / 3) << 2) + 4
+    } else {
+        ((len / 3) << 2) + r + 1
+    }
+}
+
+/// Fast base64 string decode. No error description is provided
+#[cfg(feature = "alloc")]
+#[allow(clippy::result_unit_err)]
+pub fn decode_noerr(input: &str, config: Config) -> Result<Vec<u8>, ()> {
+    config.decode(input).map_err(|_| ())
+}
+
+/// Decodes a base64 string with all error messages, useful when dealing with
+/// recoverable code logic or when a error message is required to facilitate
+/// user action.
+#[cfg(feature = "alloc")]
+pub fn decode(input: &str, config: Config) -> Result<Vec<u8>, DecodeError> {
+    config.decode(input)
+}
+
+/// Decodes a base64 str starting from `offset` with a given `align`ment.
+///
+/// The input str will no longer be a valid utf8 string, a byte slice
+/// will be returned upon success matching the alignment requirements
+///
+/// **NOTE** `offset` must be greater or equal to `align`
+///
+/// ```rust
+/// use bintext::base64;
+///
+/// // Padding of 8 (suppose it was read form a file)
+/// let mut text = "........offV6NFPD3Y=".to_string();
+///
+/// unsafe {
+///     // Decode with padding of 8 and alignment of 8
+///     let slice = base64::decode_aligned(&mut text, 8, 8, base64::STANDARD).unwrap();
+///     assert_eq!(slice, b"\xa1\xf7\xd5\xe8\xd1\x4f\x0f\x76");
+///     assert_eq!(slice.as_ptr().align_offset(8), 0);
+/// }
+/// ```
+///
+/// # Safety
+///
+/// The contents of `input` are overwritten with the decoded bytes, so it must
+/// not be used as a `str` afterwards.
+pub unsafe fn decode_aligned(
+    input: &mut str,
+    offset: usize,
+    align: usize,
+    config: Config,
+) -> Result<&mut [u8], DecodeError> {
+    config.decode_aligned(input, offset, align)
+}
+
+/// Decodes a base64 string without allocating any memory, `output` can be
+/// bigger than needed, the number of decoded bytes is returned
+pub fn decode_noalloc(
+    input: &str,
+    output: &mut [u8],
+    config: Config,
+) -> Result<usize, DecodeError> {
+    config.decode_slice(input.as_bytes(), output)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "alloc")]
+pub fn encode(input: &[u8], config: Config) -> String {
+    config.encode(input)
+}
+
+/// Encodes `input` into `output` without allocating any memory, `output` can be
+/// bigger than needed, the encoded part of it is returned
+///
+/// ```rust
+/// use bintext::base64;
+///
+/// let mut buffer = [0; 16];
+/// let text = base64::encode_noalloc(b"\xa1\xf7", &mut buffer, base64::STANDARD).unwrap();
+/// assert_eq!(text, "ofc=");
+/// ```
+pub fn encode_noalloc<'a>(
+    input: &[u8],
+    output: &'a mut [u8],
+    config: Config,
+) -> Result<&'a str, EncodeError> {
+    let n = config.encode_slice(input, output)?;
+    Ok(unsafe { core::str::from_utf8_unchecked(&output[..n]) })
+}
+
+impl Engine for Config {
+    type EncodeError = EncodeError;
+    type DecodeError = DecodeError;
+
+    #[inline]
+    fn encoded_len(&self, len: usize) -> usize {
+        encoded_len(len, self.pad)
+    }
+
+    #[inline]
+    fn decoded_len_estimate(&self, len: usize) -> usize {
+        len.div_ceil(4) * 3
+    }
+
+    #[cfg(feature = "alloc")]
+    fn encode_into(&self, output: &mut String, input: &[u8]) {
+        let n = encoded_len(input.len(), self.pad);
+        output.reserve(n);
+
+        // Only ASCII chars are written in the spare capacity
+        unsafe {
+            let v = output.as_mut_vec();
+            let len = v.len();
+            self.encode_raw(input.as_ptr(), input.len(), v.as_mut_ptr().add(len));
+            v.set_len(len + n);
+        }
+    }
+
+    fn encode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let n = encoded_len(input.len(), self.pad);
+        if output.len() < n {
+            Err(EncodeError::OutputTooSmall {
+                needed: n,
+                got: output.len(),
+            })?
+        }
+
+        unsafe { self.encode_raw(input.as_ptr(), input.len(), output.as_mut_ptr()) };
+
+        Ok(n)
+    }
+
+    fn decode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        let n = decoded_len_checked(input, output, self.pad)?;
+        unsafe { self.decode_raw(input.as_ptr(), output.as_mut_ptr(), n)? };
+
+        Ok(n)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn decode<T: AsRef<[u8]>>(&self, input: T) -> Result<Vec<u8>, DecodeError> {
+        let input = input.as_ref();
+        let n = decoded_len(input, self.pad)?;
+        let mut v = Vec::with_capacity(n);
+        unsafe {
+            self.decode_raw(input.as_ptr(), v.as_mut_ptr(), n)?;
+            v.set_len(n);
+        }
+
+        Ok(v)
+    }
+
+    unsafe fn decode_aligned<'a>(
+        &self,
+        input: &'a mut str,
+        offset: usize,
+        align: usize,
+    ) -> Result<&'a mut [u8], DecodeError> {
+        use DecodeError::*;
+
+        if !align.is_power_of_two() {
+            Err(InvalidAlignment(align))?
+        }
+
+        let bytes = input.as_bytes_mut();
+        let len = bytes.len();
+        if offset > len {
+            Err(OffsetPastEnd { offset, len })?
+        }
+
+        // Safe only when if offset is greater or equal than the alignment requirement
+        if align > 1 && offset < align {
+            Err(BadOffset)?
+        }
+
+        let n = decoded_len(&bytes[offset..], self.pad)?;
+
+        // Input and output overlap, so both are only accessed by raw pointers,
+        // the output is never after the input since `a < align <= offset`
+        let ptr = bytes.as_mut_ptr();
+        let a = ptr.align_offset(align);
+        self.decode_raw(ptr.add(offset), ptr.add(a), n)?;
+
+        Ok(core::slice::from_raw_parts_mut(ptr.add(a), n))
+    }
+}
+
+#[cfg(test)]
+mod tests_extra {
+    use super::*;
+
+    /// Emulates `_mm_shuffle_epi8`
+    fn shuffle(lut: &[u8; 16], i: u8) -> u8 {
+        if i & 0x80 != 0 {
+            0
+        } else {
+            lut[(i & 0xf) as usize]
+        }
+    }
+
+    #[test]
+    fn decoding_luts() {
+        for alphabet in [&STANDARD_ALPHABET, &URL_SAFE_ALPHABET].iter() {
+            for c in 0..=255u8 {
+                let hi = c >> 4;
+                let lo = c & 0xf;
+                let invalid =
+                    shuffle(&alphabet.decode_lo, lo) & shuffle(&alphabet.decode_hi, hi) != 0;
+                let expected = alphabet.decode[c as usize];
+                assert_eq!(invalid, expected == I, "{} {:?}", alphabet.name, c as char);
+
+                if !invalid {
+                    let delta = if c == alphabet.decode_special {
+                        alphabet.decode_special_delta
+                    } else {
+                        0
+                    };
+                    let roll = shuffle(&alphabet.decode_roll, hi.wrapping_add(delta));
+                    assert_eq!(c.wrapping_add(roll), expected, "{}", c as char);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encoding_luts() {
+        for alphabet in [&STANDARD_ALPHABET, &URL_SAFE_ALPHABET].iter() {
+            for i in 0..64u8 {
+                let mut class = i.saturating_sub(51);
+                if i < 26 {
+                    class |= 13;
+                }
+                let c = shuffle(&alphabet.encode_shift, class).wrapping_add(i);
+                assert_eq!(c, alphabet.encode[i as usize]);
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_aligned() {
+        const SAMPLES_ALIGNED: [(&[u8], &str, usize, usize); 4] = [
+            (b"\x02\x03\x04\x05", "----AgMEBQ==", 4, 4),
+            (b"\x02\x03\x04\x05", "#----AgMEBQ==", 5, 4),
+            (b"\x02\x03\x04\x05", "#--AgMEBQ==", 3, 2),
+            (b"\x02\x03\x04\x05", "AgMEBQ==", 0, 1),
+        ];
+
+        for (expected, input, offset, align) in SAMPLES_ALIGNED.iter() {
+            let mut v = input.to_string();
+            let v = unsafe { decode_aligned(&mut v, *offset, *align, STANDARD).unwrap() };
+            assert_eq!(v, *expected);
+            assert_eq!(v.as_ptr().align_offset(*align), 0);
+        }
+    }
+
+    #[test]
+    fn decoding_aligned_layout() {
+        use DecodeError::*;
+
+        // Buffer with a known alignment
+        #[repr(align(64))]
+        struct Block([u8; 192]);
+
+        let text = b"AgMEBQ==";
+        let step = if cfg!(miri) { 7 } else { 1 };
+
+        for align in [1, 2, 4, 8, 16, 32, 64].iter() {
+            for misalign in (0..64).step_by(step) {
+                for offset in 0..=64 {
+                    let mut block = Block([b'-'; 192]);
+                    let start = misalign + offset;
+                    block.0[start..start + text.len()].copy_from_slice(text);
+                    let v = &mut block.0[misalign..start + text.len()];
+                    let v = core::str::from_utf8_mut(v).unwrap();
+                    let len = v.len();
+
+                    let r = unsafe { decode_aligned(v, len + 1 + offset, *align, STANDARD) };
+                    let expected = OffsetPastEnd {
+                        offset: len + 1 + offset,
+                        len,
+                    };
+                    assert_eq!(r.unwrap_err(), expected);
+
+                    let r = unsafe { decode_aligned(v, offset, *align, STANDARD) };
+                    if *align > 1 && offset < *align {
+                        assert_eq!(r.unwrap_err(), BadOffset);
+                        assert_eq!(&block.0[start..start + text.len()], text);
+                        continue;
+                    }
+
+                    let r = r.unwrap();
+                    assert_eq!(r, b"\x02\x03\x04\x05");
+                    assert_eq!(r.as_ptr().align_offset(*align), 0);
+                }
+            }
+        }
+
+        let mut v = "--------AgMEBQ==".to_string();
+        for align in [0, 3, 6, 12, 63, usize::MAX].iter() {
+            let r = unsafe { decode_aligned(&mut v, 8, *align, STANDARD) };
+            assert_eq!(r.unwrap_err(), InvalidAlignment(*align));
+        }
+        assert_eq!(v, "--------AgMEBQ==");
+
+        let mut v = "AgME".to_string();
+        let r = unsafe { decode_aligned(&mut v, 8, 1, STANDARD) };
+        assert_eq!(r.unwrap_err(), OffsetPastEnd { offset: 8, len: 4 });
+    }
+
+    #[test]
+    fn encoding_noalloc() {
+        use EncodeError::*;
+
+        let mut v = [0; 7];
+        assert_eq!(
+            encode_noalloc(b"\x02\x03\x04\x05", &mut v, STANDARD),
+            Err(OutputTooSmall { needed: 8, got: 7 })
+        );
+        assert_eq!(
+            encode_noalloc(b"\x02\x03\x04\x05", &mut v, STANDARD_NO_PAD),
+            Ok("AgMEBQ")
+        );
+        let mut v = [0; 12];
+        assert_eq!(
+            encode_noalloc(b"\x02\x03\x04\x05", &mut v, STANDARD),
+            Ok("AgMEBQ==")
+        );
+        assert_eq!(encode_noalloc(b"", &mut [], STANDARD), Ok(""));
+    }
+
+    #[test]
+    fn decoding_noalloc() {
+        use DecodeError::*;
+
+        let mut v = [0; 4];
+        assert_eq!(
+            decode_noalloc("AgMEBQY=", &mut v, STANDARD),
+            Err(OutputTooSmall { needed: 5, got: 4 })
+        );
+        assert_eq!(decode_noalloc("AgME", &mut v, STANDARD), Ok(3));
+        assert_eq!(v, [2, 3, 4, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn backends() {
+        let input: Vec<u8> = (0..200).map(|i| (i * 37) as u8).collect();
+        let implemented = [Backend::Fallback, Backend::Ssse3, Backend::Avx2];
+
+        for backend in crate::dispatch::BACKENDS.iter() {
+            let config = match URL_SAFE.backend(*backend) {
+                Ok(config) => config,
+                Err(e) => {
+                    assert_eq!(e, UnsupportedBackend(*backend));
+                    assert!(!backend.is_supported() || !implemented.contains(backend));
+                    continue;
+                }
+            };
+            assert!(implemented.contains(backend));
+            assert_eq!(config.functions().backend, *backend);
+
+            for len in 0..input.len() {
+                let text = encode(&input[..len], config);
+                assert_eq!(text, encode(&input[..len], URL_SAFE), "{:?}", backend);
+                assert_eq!(decode(&text, config).unwrap(), &input[..len]);
+            }
+
+            let r = decode("AgMEB@==", config);
+            assert_eq!(r, decode("AgMEB@==", URL_SAFE), "{:?}", backend);
+            assert!(r.is_err());
+            assert_eq!(config.pad(false).functions().backend, *backend);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn engine() {
+        let input: Vec<u8> = (0..100).map(|i| (i * 37) as u8).collect();
+
+        for config in [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD].iter() {
+            for len in 0..input.len() {
+                let text = config.encode(&input[..len]);
+                assert_eq!(text.len(), config.encoded_len(len));
+                assert!(config.decoded_len_estimate(text.len()) >= len);
+
+                let mut v = "#".to_string();
+                config.encode_into(&mut v, &input[..len]);
+                assert_eq!(&v[1..], text);
+
+                let mut v = vec![0; text.len() + 3];
+                assert_eq!(
+                    encode_noalloc(&input[..len], &mut v, *config),
+                    Ok(&text[..])
+                );
+                assert_eq!(config.encode_slice(&input[..len], &mut v), Ok(text.len()));
+                assert_eq!(&v[..text.len()], text.as_bytes());
+
+                let mut v = vec![0; config.decoded_len_estimate(text.len())];
+                let n = config.decode_slice(text.as_bytes(), &mut v).unwrap();
+                assert_eq!(&v[..n], &input[..len]);
+            }
+        }
+    }
+}
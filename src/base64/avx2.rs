@@ -0,0 +1,218 @@
+#![allow(dead_code)]
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use super::*;
+use core::ptr::copy_nonoverlapping;
+
+// Same algorithm of the SSSE3 impl, each 128 bits lane works just like it
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub unsafe fn decode(input: &str, config: Config) -> Result<Vec<u8>, DecodeError> {
+    let n = decoded_len(input.as_bytes(), config.pad)?;
+    let mut v = Vec::with_capacity(n);
+    decode_raw(input.as_ptr(), v.as_mut_ptr(), n, config)?;
+    v.set_len(n);
+
+    Ok(v)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+unsafe fn load_lut(lut: &[u8; 16]) -> __m256i {
+    _mm256_broadcastsi128_si256(_mm_loadu_si128(lut.as_ptr() as *const __m128i))
+}
+
+/// Decodes `input` into `output` returning the number of decoded bytes
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module
+pub unsafe fn decode_noalloc(
+    input: &[u8],
+    output: &mut [u8],
+    config: Config,
+) -> Result<usize, DecodeError> {
+    let n = decoded_len_checked(input, output, config.pad)?;
+    decode_raw(input.as_ptr(), output.as_mut_ptr(), n, config)?;
+
+    Ok(n)
+}
+
+/// Decodes `n` bytes from the chars at `src` into `dst`, `n` must be the
+/// length returned by [`decoded_len`] for the chars
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for reads of the chars of the `n` bytes and `dst` for `n` writes,
+/// they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    dst: *mut u8,
+    n: usize,
+    config: Config,
+) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    // Chars of complete quads without any padding
+    let c = (n / 3) << 2;
+
+    // Constants
+    let alphabet = config.alphabet;
+    let lut_lo = load_lut(&alphabet.decode_lo);
+    let lut_hi = load_lut(&alphabet.decode_hi);
+    let lut_roll = load_lut(&alphabet.decode_roll);
+    let special = _mm256_set1_epi8(alphabet.decode_special as i8);
+    let special_delta = _mm256_set1_epi8(alphabet.decode_special_delta as i8);
+
+    let x0f = _mm256_set1_epi8(0x0f);
+    let zero = _mm256_setzero_si256();
+    let merge0 = _mm256_set1_epi32(0x01400140);
+    let merge1 = _mm256_set1_epi32(0x00011000);
+    #[rustfmt::skip]
+    let pack = _mm256_setr_epi8(
+        2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1,
+        2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1,
+    );
+
+    let p = src;
+    let b = dst;
+    let mut i = 0;
+    let mut j = 0;
+
+    // Main loop loop
+    while i + 32 <= c {
+        let slice = _mm256_loadu_si256(p.add(i) as *const __m256i);
+
+        let hi = _mm256_and_si256(_mm256_srli_epi32(slice, 4), x0f);
+        let lo = _mm256_and_si256(slice, x0f);
+
+        // Chars are valid when the bitsets of both nibbles have nothing in common
+        let invalid = _mm256_and_si256(
+            _mm256_shuffle_epi8(lut_lo, lo),
+            _mm256_shuffle_epi8(lut_hi, hi),
+        );
+        let ok = _mm256_movemask_epi8(_mm256_cmpeq_epi8(invalid, zero)) as u32;
+        if ok != 0xffffffff {
+            // First unset bit is the first invalid char of the slice
+            let k = (!ok).trailing_zeros() as usize;
+            Err(InvalidCharAt(i + k))?
+        }
+
+        // Translate chars to their 6 bits values
+        let eq = _mm256_cmpeq_epi8(slice, special);
+        let roll = _mm256_shuffle_epi8(
+            lut_roll,
+            _mm256_add_epi8(hi, _mm256_and_si256(eq, special_delta)),
+        );
+        let values = _mm256_add_epi8(slice, roll);
+
+        // Merge each 4 values of 6 bits into 3 bytes
+        let dec = _mm256_maddubs_epi16(values, merge0);
+        let dec = _mm256_madd_epi16(dec, merge1);
+        let dec = _mm256_shuffle_epi8(dec, pack);
+
+        // Saves the final result, 12 bytes of each lane
+        let ptr = &dec as *const _ as *const u8;
+        copy_nonoverlapping(ptr, b.add(j), 12);
+        copy_nonoverlapping(ptr.add(16), b.add(j + 12), 12);
+
+        i += 32;
+        j += 24;
+    }
+
+    // Handle the remaining of bytes
+    fallback::decode_tail(p.add(i), i, b.add(j), n - j, config)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub unsafe fn encode(input: &[u8], config: Config) -> String {
+    let n = encoded_len(input.len(), config.pad);
+    let mut v = Vec::with_capacity(n);
+    encode_raw(input.as_ptr(), input.len(), v.as_mut_ptr(), config);
+    v.set_len(n);
+    String::from_utf8_unchecked(v)
+}
+
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads and `dst` for writes of the encoded length,
+/// without overlapping
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, config: Config) {
+    // Constants
+    let lut = load_lut(&config.alphabet.encode_shift);
+    #[rustfmt::skip]
+    let spread = _mm256_setr_epi8(
+        1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10,
+        1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10,
+    );
+    let mask0 = _mm256_set1_epi32(0x0fc0fc00);
+    let mul0 = _mm256_set1_epi32(0x04000040);
+    let mask1 = _mm256_set1_epi32(0x003f03f0);
+    let mul1 = _mm256_set1_epi32(0x01000010);
+    let x33 = _mm256_set1_epi8(51);
+    let x1a = _mm256_set1_epi8(26);
+    let x0d = _mm256_set1_epi8(13);
+
+    let c = len;
+    let p = src;
+    let b = dst;
+    let mut i = 0;
+    let mut j = 0;
+
+    // * NOTE: each lane loads 16 bytes but only 12 are encoded
+    while i + 28 <= c {
+        let slice = _mm256_inserti128_si256(
+            _mm256_castsi128_si256(_mm_loadu_si128(p.add(i) as *const __m128i)),
+            _mm_loadu_si128(p.add(i + 12) as *const __m128i),
+            1,
+        );
+
+        // Spread each 3 bytes in 4 u8 lanes, then move each 6 bits
+        // index to the bottom of its own lane
+        let slice = _mm256_shuffle_epi8(slice, spread);
+        let t0 = _mm256_mulhi_epu16(_mm256_and_si256(slice, mask0), mul0);
+        let t1 = _mm256_mullo_epi16(_mm256_and_si256(slice, mask1), mul1);
+        let indexes = _mm256_or_si256(t0, t1);
+
+        // Classify each index to pick the offset of its range
+        let class = _mm256_subs_epu8(indexes, x33);
+        let less = _mm256_cmpgt_epi8(x1a, indexes);
+        let class = _mm256_or_si256(class, _mm256_and_si256(less, x0d));
+        let enc = _mm256_add_epi8(_mm256_shuffle_epi8(lut, class), indexes);
+
+        _mm256_storeu_si256(b.add(j) as *mut _, enc);
+
+        i += 24;
+        j += 32;
+    }
+
+    // loop through the remaining bytes
+    fallback::encode_tail(p.add(i), c - i, b.add(j), config);
+}
+
+#[inline(always)]
+pub fn meet_requirements() -> bool {
+    crate::is_x86_feature_enabled!("avx2")
+}
+
+crate::tests_base64!(
+    super::encode,
+    super::decode,
+    super::decode_noalloc,
+    super::meet_requirements
+);
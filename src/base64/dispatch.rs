@@ -0,0 +1,98 @@
+//! Base64 functions of each backend, the table of the selected one is cached
+
+use crate::dispatch::{Backend, Selected};
+
+use super::{fallback, Config, DecodeError};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::{avx2, sse2};
+
+type DecodeRaw = unsafe fn(*const u8, *mut u8, usize, Config) -> Result<(), DecodeError>;
+type EncodeRaw = unsafe fn(*const u8, usize, *mut u8, Config);
+
+/// Functions of a single backend
+pub(crate) struct Functions {
+    pub backend: Backend,
+    pub decode_raw: DecodeRaw,
+    pub encode_raw: EncodeRaw,
+}
+
+static FALLBACK: Functions = Functions {
+    backend: Backend::Fallback,
+    decode_raw: fallback::decode_raw,
+    encode_raw: fallback::encode_tail,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static SSE2: Functions = Functions {
+    backend: Backend::Ssse3,
+    decode_raw: sse2::decode_raw,
+    encode_raw: sse2::encode_raw,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static AVX2: Functions = Functions {
+    backend: Backend::Avx2,
+    decode_raw: avx2::decode_raw,
+    encode_raw: avx2::encode_raw,
+};
+
+/// Table of `backend`, `None` when base64 doesn't implement it
+pub(crate) fn table(backend: Backend) -> Option<&'static Functions> {
+    match backend {
+        Backend::Fallback => Some(&FALLBACK),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Ssse3 => Some(&SSE2),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Avx2 => Some(&AVX2),
+        _ => None,
+    }
+}
+
+/// Fastest table that isn't above `backend`
+fn pick(backend: Backend) -> &'static Functions {
+    match backend {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Avx2 | Backend::Avx512 => &AVX2,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Ssse3 => &SSE2,
+        _ => &FALLBACK,
+    }
+}
+
+/// Table of the selected backend, null until the first call
+static SELECTED: Selected<Functions> = Selected::new(pick);
+
+/// Functions of the [current](Backend::current) backend, the detection is
+/// only done once
+#[inline(always)]
+pub(crate) fn functions() -> &'static Functions {
+    // Picked at compile time, neither the CPU nor the environment are checked
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if crate::is_sse_ubiquitous!() {
+        return &SSE2;
+    }
+
+    SELECTED.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::BACKENDS;
+
+    #[test]
+    fn picking() {
+        assert!(core::ptr::eq(functions(), pick(Backend::current())));
+        assert!(functions().backend <= Backend::current());
+
+        for backend in BACKENDS.iter() {
+            let picked = pick(*backend);
+            assert!(picked.backend <= *backend);
+            assert!(core::ptr::eq(table(picked.backend).unwrap(), picked));
+            if let Some(table) = table(*backend) {
+                assert_eq!(table.backend, *backend);
+            }
+        }
+    }
+}
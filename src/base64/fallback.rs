@@ -0,0 +1,186 @@
+#![allow(dead_code)]
+
+use super::{decoded_len_checked, Config, DecodeError};
+
+#[cfg(feature = "alloc")]
+use super::{decoded_len, encoded_len};
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub fn decode(input: &str, config: Config) -> Result<Vec<u8>, DecodeError> {
+    let n = decoded_len(input.as_bytes(), config.pad)?;
+    let mut v = Vec::with_capacity(n);
+    unsafe {
+        decode_tail(input.as_ptr(), 0, v.as_mut_ptr(), n, config)?;
+        v.set_len(n);
+    }
+
+    Ok(v)
+}
+
+/// Decodes `input` into `output` returning the number of decoded bytes
+pub fn decode_noalloc(
+    input: &[u8],
+    output: &mut [u8],
+    config: Config,
+) -> Result<usize, DecodeError> {
+    let n = decoded_len_checked(input, output, config.pad)?;
+    unsafe { decode_tail(input.as_ptr(), 0, output.as_mut_ptr(), n, config)? };
+
+    Ok(n)
+}
+
+/// Decodes `n` bytes from the chars at `src` into `dst`, `n` must be the
+/// length returned by [`decoded_len`] for the remaining chars
+///
+/// # Safety
+///
+/// `src` must be valid for reads of the chars of the `n` bytes and `dst`
+/// for `n` writes, they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    dst: *mut u8,
+    n: usize,
+    config: Config,
+) -> Result<(), DecodeError> {
+    decode_tail(src, 0, dst, n, config)
+}
+
+/// Decodes the remaining quads at `src` followed by its last incomplete group
+/// into the `n` remaining bytes at `dst`.
+///
+/// `pos` is the position of `src` in the original string, used in the errors.
+///
+/// # Safety
+///
+/// Same of [`decode_raw`]
+pub unsafe fn decode_tail(
+    src: *const u8,
+    pos: usize,
+    dst: *mut u8,
+    n: usize,
+    config: Config,
+) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let table = &config.alphabet.decode;
+    let quads = n / 3;
+
+    for k in 0..quads {
+        let q = src.add(k << 2);
+        let v = [
+            table[*q as usize],
+            table[*q.add(1) as usize],
+            table[*q.add(2) as usize],
+            table[*q.add(3) as usize],
+        ];
+
+        if (v[0] | v[1] | v[2] | v[3]) > 63 {
+            let i = v.iter().position(|v| *v > 63).unwrap();
+            Err(InvalidCharAt(pos + (k << 2) + i))?
+        }
+
+        let b = dst.add(k * 3);
+        *b = (v[0] << 2) | (v[1] >> 4);
+        *b.add(1) = (v[1] << 4) | (v[2] >> 2);
+        *b.add(2) = (v[2] << 6) | v[3];
+    }
+
+    let r = n - quads * 3;
+    if r > 0 {
+        let pos = pos + (quads << 2);
+        let q = src.add(quads << 2);
+        let mut v = [0u8; 3];
+        for (i, v) in v.iter_mut().enumerate().take(r + 1) {
+            *v = table[*q.add(i) as usize];
+            if *v > 63 {
+                Err(InvalidCharAt(pos + i))?
+            }
+        }
+
+        // The last char can't have any unused bit set
+        if (r == 1 && v[1] & 0xf != 0) || (r == 2 && v[2] & 0x3 != 0) {
+            Err(InvalidCharAt(pos + r))?
+        }
+
+        let b = dst.add(quads * 3);
+        *b = (v[0] << 2) | (v[1] >> 4);
+        if r == 2 {
+            *b.add(1) = (v[1] << 4) | (v[2] >> 2);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub fn encode(input: &[u8], config: Config) -> String {
+    let n = encoded_len(input.len(), config.pad);
+    let mut v = Vec::with_capacity(n);
+    unsafe {
+        encode_tail(input.as_ptr(), input.len(), v.as_mut_ptr(), config);
+        v.set_len(n);
+        String::from_utf8_unchecked(v)
+    }
+}
+
+/// Encodes the remaining `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// `src` must be valid for `len` reads and `dst` for writes of the encoded
+/// length, without overlapping
+pub unsafe fn encode_tail(src: *const u8, len: usize, dst: *mut u8, config: Config) {
+    let table = &config.alphabet.encode;
+    let triples = len / 3;
+
+    for k in 0..triples {
+        let t = src.add(k * 3);
+        let n = ((*t as usize) << 16) | ((*t.add(1) as usize) << 8) | (*t.add(2) as usize);
+        let b = dst.add(k << 2);
+        *b = table[n >> 18];
+        *b.add(1) = table[(n >> 12) & 63];
+        *b.add(2) = table[(n >> 6) & 63];
+        *b.add(3) = table[n & 63];
+    }
+
+    let t = src.add(triples * 3);
+    let b = dst.add(triples << 2);
+    let r = len - triples * 3;
+    match r {
+        1 => {
+            let x = *t;
+            *b = table[(x >> 2) as usize];
+            *b.add(1) = table[((x << 4) & 63) as usize];
+        }
+        2 => {
+            let (x, y) = (*t, *t.add(1));
+            *b = table[(x >> 2) as usize];
+            *b.add(1) = table[(((x << 4) | (y >> 4)) & 63) as usize];
+            *b.add(2) = table[((y << 2) & 63) as usize];
+        }
+        _ => return,
+    }
+
+    if config.pad {
+        for i in (r + 1)..4 {
+            *b.add(i) = b'=';
+        }
+    }
+}
+
+#[inline(always)]
+#[allow(dead_code)]
+pub fn meet_requirements() -> bool {
+    true
+}
+
+crate::tests_base64!(
+    super::encode,
+    super::decode,
+    super::decode_noalloc,
+    super::meet_requirements
+);
@@ -0,0 +1,170 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! tests_base64 {
+    ($encode:path, $decode:path, $decode_noalloc:path, $feat:path) => {
+        #[cfg(all(test, feature = "alloc"))]
+        mod tests {
+            use $crate::base64::*;
+
+            const SAMPLES: [(&[u8], &str); 7] = [
+                (b"", ""),
+                (b"f", "Zg=="),
+                (b"fo", "Zm8="),
+                (b"foo", "Zm9v"),
+                (b"foob", "Zm9vYg=="),
+                (b"fooba", "Zm9vYmE="),
+                (b"foobar", "Zm9vYmFy"),
+            ];
+
+            fn configs() -> [(Config, ::base64::Config); 4] {
+                [
+                    (STANDARD, ::base64::STANDARD),
+                    (STANDARD_NO_PAD, ::base64::STANDARD_NO_PAD),
+                    (URL_SAFE, ::base64::URL_SAFE),
+                    (URL_SAFE_NO_PAD, ::base64::URL_SAFE_NO_PAD),
+                ]
+            }
+
+            /// Bytes of every length up to 200, covering all the symbols,
+            /// Miri is too slow to check them all
+            fn data() -> Vec<Vec<u8>> {
+                (0..200)
+                    .step_by(if cfg!(miri) { 23 } else { 1 })
+                    .map(|n| (0..n).map(|i| (i * 97 + n * 13) as u8).collect())
+                    .collect()
+            }
+
+            #[test]
+            #[allow(unused_unsafe)]
+            fn encoding() {
+                if !$feat() {
+                    panic!("doesn't have the required instruction set");
+                }
+
+                for (input, expected) in SAMPLES.iter() {
+                    let r = unsafe { $encode(input, STANDARD) };
+                    assert_eq!(r, *expected);
+                }
+
+                for (config, other) in configs().iter() {
+                    for input in data().iter() {
+                        let r = unsafe { $encode(input, *config) };
+                        assert_eq!(r, ::base64::encode_config(input, *other));
+                    }
+                }
+            }
+
+            #[test]
+            #[allow(unused_unsafe)]
+            fn decoding() {
+                if !$feat() {
+                    panic!("doesn't have the required instruction set");
+                }
+
+                for (expected, input) in SAMPLES.iter() {
+                    let r = unsafe { $decode(input, STANDARD) };
+                    assert_eq!(r.unwrap(), *expected);
+                }
+
+                for (config, other) in configs().iter() {
+                    for expected in data().iter() {
+                        let input = ::base64::encode_config(expected, *other);
+                        let r = unsafe { $decode(&input, *config) };
+                        assert_eq!(&r.unwrap(), expected);
+                    }
+                }
+            }
+
+            #[test]
+            #[allow(unused_unsafe)]
+            fn decoding_errors() {
+                use DecodeError::*;
+
+                if !$feat() {
+                    panic!("doesn't have the required instruction set");
+                }
+
+                for (config, other) in configs().iter() {
+                    for input in data().iter() {
+                        let input = ::base64::encode_config(input, *other);
+                        let symbols = input.trim_end_matches('=').len();
+                        for i in (0..symbols).step_by(if cfg!(miri) { 37 } else { 1 }) {
+                            for c in ["!", " ", ".", "\x7f", "="].iter() {
+                                // Could be a valid padding
+                                if *c == "=" && i + 4 >= input.len() {
+                                    continue;
+                                }
+
+                                let mut v = input.to_string();
+                                v.replace_range(i..i + 1, c);
+                                let r = unsafe { $decode(&v, *config) };
+                                assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", v);
+                            }
+
+                            // Non ascii chars
+                            if i + 2 <= symbols {
+                                let mut v = input.to_string();
+                                v.replace_range(i..i + 2, "é");
+                                let r = unsafe { $decode(&v, *config) };
+                                assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", v);
+                            }
+                        }
+                    }
+                }
+
+                // Wrong alphabet
+                let r = unsafe { $decode("Zm9v+/8A", URL_SAFE) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(4));
+                let r = unsafe { $decode("Zm9v-_8A", STANDARD) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(4));
+
+                // Unused bits
+                let r = unsafe { $decode("Zh==", STANDARD) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(1));
+                let r = unsafe { $decode("Zm9=", STANDARD) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(2));
+
+                // Length and padding
+                let r = unsafe { $decode("Zm9vY", STANDARD_NO_PAD) };
+                assert_eq!(r.unwrap_err(), InvalidLength);
+                let r = unsafe { $decode("Zm9vYg", STANDARD) };
+                assert_eq!(r.unwrap_err(), InvalidLength);
+                let r = unsafe { $decode("Zm9vYg==", STANDARD_NO_PAD) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(6));
+                let r = unsafe { $decode("Zm9v====", STANDARD) };
+                assert_eq!(r.unwrap_err(), InvalidCharAt(4));
+            }
+
+            #[test]
+            #[allow(unused_unsafe)]
+            fn decoding_noalloc() {
+                use DecodeError::*;
+
+                if !$feat() {
+                    panic!("doesn't have the required instruction set");
+                }
+
+                for expected in data().iter().skip(1) {
+                    let input = ::base64::encode_config(expected, ::base64::STANDARD);
+                    let n = expected.len();
+
+                    let mut v = vec![0; n - 1];
+                    let r = unsafe { $decode_noalloc(input.as_bytes(), &mut v[..], STANDARD) };
+                    assert_eq!(
+                        r.unwrap_err(),
+                        OutputTooSmall {
+                            needed: n,
+                            got: n - 1
+                        }
+                    );
+
+                    let mut v = vec![0xcc; n + 5];
+                    let r = unsafe { $decode_noalloc(input.as_bytes(), &mut v[..], STANDARD) };
+                    assert_eq!(r.unwrap(), n);
+                    assert_eq!(&v[..n], &expected[..]);
+                    assert_eq!(&v[n..], &[0xcc; 5]);
+                }
+            }
+        }
+    };
+}
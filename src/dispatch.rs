@@ -0,0 +1,256 @@
+//! Backend selection shared by every codec, the CPU features are detected
+//! only once and each codec caches the table of its functions for the
+//! selected backend.
+
+use core::error::Error;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+
+/// Environment variable that caps the backend picked automatically for hex and
+/// base64, only read with the `std` feature and ignored with `sse_ubiquitous`.
+///
+/// Accepts `fallback`, `swar`, `ssse3` (or `sse2`), `avx2` and `avx512` in any
+/// case, the first detection panics on any other non empty value.
+pub const BACKEND_VAR: &str = "BINTEXT_BACKEND";
+
+/// Implementation used to encode and decode, ordered from the slowest to the fastest.
+///
+/// The free functions use the [current](Backend::current) one, with the
+/// `sse_ubiquitous` feature it's always [`Ssse3`](Backend::Ssse3) on x86 and
+/// the [`BACKEND_VAR`] cap is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Portable implementation handling a byte at the time, always supported
+    Fallback,
+    /// Portable implementation handling 8 bytes at the time, always supported
+    Swar,
+    /// SSE2 and SSSE3
+    Ssse3,
+    Avx2,
+    /// AVX512BW and AVX512VBMI
+    Avx512,
+}
+
+/// Every backend in the order of their discriminants
+pub(crate) const BACKENDS: [Backend; 5] = [
+    Backend::Fallback,
+    Backend::Swar,
+    Backend::Ssse3,
+    Backend::Avx2,
+    Backend::Avx512,
+];
+
+impl Backend {
+    /// Tells if the CPU supports this backend
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Fallback | Backend::Swar => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Ssse3 => {
+                crate::is_sse_ubiquitous!() || crate::is_x86_feature_enabled!("ssse3")
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => crate::is_x86_feature_enabled!("avx2"),
+            // Miri can't run AVX-512 instructions
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx512 => {
+                !cfg!(miri)
+                    && crate::is_x86_feature_enabled!("avx512bw")
+                    && crate::is_x86_feature_enabled!("avx512vbmi")
+            }
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            _ => false,
+        }
+    }
+
+    /// Fastest backend supported by the CPU
+    pub fn best() -> Self {
+        [Backend::Avx512, Backend::Avx2, Backend::Ssse3]
+            .iter()
+            .copied()
+            .find(|backend| backend.is_supported())
+            .unwrap_or(Backend::Swar)
+    }
+
+    /// Backend picked for the free functions of [`hex`](mod@crate::hex) and
+    /// [`base64`](crate::base64), the [`best`](Self::best) one capped by the
+    /// `BINTEXT_BACKEND` environment variable, base64 uses the fastest of its
+    /// backends that isn't above it.
+    ///
+    /// With `sse_ubiquitous` it's [`Ssse3`](Backend::Ssse3) on x86, picked at
+    /// compile time without reading the environment.
+    ///
+    /// # Panics
+    ///
+    /// On the first call, when `BINTEXT_BACKEND` isn't a backend name
+    pub fn current() -> Self {
+        // Picked at compile time, neither the CPU nor the environment are checked
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        if crate::is_sse_ubiquitous!() {
+            return Backend::Ssse3;
+        }
+
+        match CURRENT.load(Ordering::Relaxed) {
+            0 => detect(),
+            n => BACKENDS[n as usize - 1],
+        }
+    }
+
+    /// Parses the names accepted by the `BINTEXT_BACKEND` environment variable
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            ("fallback", Backend::Fallback),
+            ("swar", Backend::Swar),
+            ("ssse3", Backend::Ssse3),
+            ("sse2", Backend::Ssse3),
+            ("avx2", Backend::Avx2),
+            ("avx512", Backend::Avx512),
+        ]
+        .iter()
+        .find(|(n, _)| name.eq_ignore_ascii_case(n))
+        .map(|&(_, backend)| backend)
+    }
+}
+
+/// The requested backend isn't supported by the CPU or isn't implemented by
+/// the codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedBackend(pub Backend);
+
+impl fmt::Display for UnsupportedBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the {:?} backend isn't supported by this CPU or codec",
+            self.0
+        )
+    }
+}
+
+impl Error for UnsupportedBackend {}
+
+/// Selected backend plus one, zero until the first call
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Best backend allowed by the `cap` name, an empty one doesn't cap it
+fn select(cap: Option<&str>) -> Backend {
+    let best = Backend::best();
+    match cap {
+        Some(name) if !name.is_empty() => match Backend::from_name(name) {
+            Some(cap) => best.min(cap),
+            None => panic!("unknown backend {:?} in {}", name, BACKEND_VAR),
+        },
+        _ => best,
+    }
+}
+
+/// Best backend capped by [`BACKEND_VAR`]
+#[cfg(feature = "std")]
+fn selected() -> Backend {
+    select(std::env::var(BACKEND_VAR).ok().as_deref())
+}
+
+/// Best backend, the environment can't be read without `std`
+#[cfg(not(feature = "std"))]
+fn selected() -> Backend {
+    select(None)
+}
+
+#[cold]
+fn detect() -> Backend {
+    let backend = selected();
+    // Racing threads will store the same backend
+    CURRENT.store(backend as u8 + 1, Ordering::Relaxed);
+    backend
+}
+
+/// Table of a codec for the [current](Backend::current) backend, only picked
+/// on the first call
+pub(crate) struct Selected<T: 'static> {
+    table: AtomicPtr<T>,
+    pick: fn(Backend) -> &'static T,
+}
+
+impl<T> Selected<T> {
+    pub const fn new(pick: fn(Backend) -> &'static T) -> Self {
+        Selected {
+            table: AtomicPtr::new(ptr::null_mut()),
+            pick,
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> &'static T {
+        let table = self.table.load(Ordering::Relaxed);
+        if table.is_null() {
+            return self.detect();
+        }
+
+        // Only `'static` tables are ever stored
+        unsafe { &*table }
+    }
+
+    #[cold]
+    fn detect(&self) -> &'static T {
+        let table = (self.pick)(Backend::current());
+        // Racing threads will store the same table
+        self.table
+            .store(table as *const T as *mut T, Ordering::Relaxed);
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached() {
+        if cfg!(feature = "sse_ubiquitous") {
+            assert_eq!(Backend::current(), Backend::Ssse3);
+            return;
+        }
+
+        let cap = std::env::var(BACKEND_VAR).ok();
+        assert_eq!(Backend::current(), select(cap.as_deref()));
+        assert_eq!(
+            BACKENDS[CURRENT.load(Ordering::Relaxed) as usize - 1],
+            Backend::current()
+        );
+
+        static TABLE: Selected<Backend> = Selected::new(|backend| &BACKENDS[backend as usize]);
+        assert_eq!(*TABLE.get(), Backend::current());
+        assert!(ptr::eq(TABLE.get(), TABLE.table.load(Ordering::Relaxed)));
+    }
+
+    #[test]
+    fn capping() {
+        let best = Backend::best();
+        assert_eq!(select(None), best);
+        assert_eq!(select(Some("")), best);
+        assert_eq!(select(Some("fallback")), Backend::Fallback);
+        assert_eq!(select(Some("swar")), Backend::Swar);
+        assert_eq!(select(Some("SSE2")), best.min(Backend::Ssse3));
+        assert_eq!(select(Some("avx2")), best.min(Backend::Avx2));
+        assert_eq!(select(Some("avx512")), best);
+        assert!(Backend::current() <= best);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown backend \"swr\" in BINTEXT_BACKEND")]
+    fn capping_unknown() {
+        select(Some("swr"));
+    }
+
+    #[test]
+    fn supported() {
+        assert!(Backend::Fallback.is_supported());
+        assert!(Backend::Swar.is_supported());
+        assert!(Backend::best().is_supported());
+        for (i, backend) in BACKENDS.iter().enumerate() {
+            assert_eq!(*backend as usize, i);
+        }
+    }
+}
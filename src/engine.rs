@@ -0,0 +1,82 @@
+//! Interface shared by every codec of this crate
+
+use core::error::Error;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+
+/// Binary to text codec, implemented by [`hex::Hex`](crate::hex::Hex) and
+/// [`base64::Config`](crate::base64::Config).
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use bintext::{base64, hex, Engine};
+///
+/// fn roundtrip<E: Engine>(engine: E, input: &[u8]) -> String {
+///     let text = engine.encode(input);
+///     assert_eq!(text.len(), engine.encoded_len(input.len()));
+///     assert_eq!(engine.decode(&text).unwrap(), input);
+///
+///     let mut buffer = [0; 16];
+///     let n = engine.encode_slice(input, &mut buffer).unwrap();
+///     assert_eq!(&buffer[..n], text.as_bytes());
+///     text
+/// }
+///
+/// assert_eq!(roundtrip(hex::Hex::new(), b"\xa1\xf7"), "a1f7");
+/// assert_eq!(roundtrip(base64::STANDARD, b"\xa1\xf7"), "ofc=");
+/// # }
+/// ```
+pub trait Engine {
+    type EncodeError: Error;
+    type DecodeError: Error;
+
+    /// Number of chars needed to encode `len` bytes
+    fn encoded_len(&self, len: usize) -> usize;
+
+    /// Upper bound of the bytes decoded from `len` chars, the exact number
+    /// may depend on the chars themselves
+    fn decoded_len_estimate(&self, len: usize) -> usize;
+
+    /// Appends the encoded `input` to `output`
+    #[cfg(feature = "alloc")]
+    fn encode_into(&self, output: &mut String, input: &[u8]);
+
+    #[cfg(feature = "alloc")]
+    fn encode(&self, input: &[u8]) -> String {
+        let mut output = String::with_capacity(self.encoded_len(input.len()));
+        self.encode_into(&mut output, input);
+        output
+    }
+
+    /// Encodes `input` into `output` returning the number of encoded chars,
+    /// `output` can be bigger than needed
+    fn encode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, Self::EncodeError>;
+
+    /// Decodes `input` into `output` returning the number of decoded bytes,
+    /// `output` can be bigger than needed
+    fn decode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, Self::DecodeError>;
+
+    #[cfg(feature = "alloc")]
+    fn decode<T: AsRef<[u8]>>(&self, input: T) -> Result<Vec<u8>, Self::DecodeError> {
+        let input = input.as_ref();
+        let mut v = vec![0; self.decoded_len_estimate(input.len())];
+        let n = self.decode_slice(input, &mut v)?;
+        v.truncate(n);
+        Ok(v)
+    }
+
+    /// Decodes `input` starting from `offset` in place, the decoded bytes
+    /// start at the first position of `input` that matches the `align`ment.
+    ///
+    /// # Safety
+    ///
+    /// The contents of `input` are overwritten with the decoded bytes, so it
+    /// must not be used as a `str` afterwards.
+    unsafe fn decode_aligned<'a>(
+        &self,
+        input: &'a mut str,
+        offset: usize,
+        align: usize,
+    ) -> Result<&'a mut [u8], Self::DecodeError>;
+}
@@ -1,15 +1,60 @@
 //! Hex encoding and decoding
 
-use std::error::Error;
-use std::fmt;
+// SIMD tables are only used by the x86 backends
+#![cfg_attr(
+    not(any(target_arch = "x86", target_arch = "x86_64")),
+    allow(dead_code)
+)]
 
+use core::error::Error;
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ptr;
+use core::slice;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::{Engine, Pod};
+
+#[cfg(feature = "alloc")]
+mod aligned;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod avx2;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx512;
+mod chunked;
+pub(crate) mod dispatch;
+mod engine;
 mod fallback;
+mod header;
+mod literal;
+mod skip;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod sse2;
+#[cfg(feature = "std")]
+mod stream;
+mod swar;
 
 mod support;
 mod tests;
 
+pub use crate::dispatch::{Backend, UnsupportedBackend, BACKEND_VAR};
+#[cfg(feature = "alloc")]
+pub use aligned::AlignedHexBuf;
+pub use chunked::ChunkedDecoder;
+pub use dispatch::{with_backend, WithBackend};
+pub use engine::Hex;
+pub use header::decode_aligned_auto;
+#[cfg(feature = "alloc")]
+pub use header::{encode_aligned_auto, encode_aligned_auto_into};
+pub use literal::decode_array;
+#[cfg(feature = "alloc")]
+pub use skip::decode_skipping;
+pub use skip::SkipSet;
+#[cfg(feature = "std")]
+pub use stream::{Decoder, Encoder};
+
 /// Invalid nibble
 const I: u8 = 255;
 
@@ -34,6 +79,20 @@ const HEX_NIBBLE_DECODE: [u8; 256] = [
     I,   I,   I,   I,   I,   I,   I,   I,   I,   I,  I,  I,  I,  I,  I,  I
 ];
 
+/// Keeps only the nibbles of the chars not in `from..=to`
+const fn nibbles_without(from: u8, to: u8) -> [u8; 256] {
+    let mut table = HEX_NIBBLE_DECODE;
+    let mut c = from;
+    while c <= to {
+        table[c as usize] = I;
+        c += 1;
+    }
+    table
+}
+
+const HEX_NIBBLE_DECODE_LOWER: [u8; 256] = nibbles_without(b'A', b'F');
+const HEX_NIBBLE_DECODE_UPPER: [u8; 256] = nibbles_without(b'a', b'f');
+
 // Inverted to make error handle works
 const N: u8 = 0;
 const HEX_DECODE_64LUT_X30_1: i64 = i64::from_le_bytes([!0x8, !0x9, N, N, N, N, N, N]); // [0-9]
@@ -60,28 +119,116 @@ const HEX_ENCODE: [u8; 512] =
        e0e1e2e3e4e5e6e7e8e9eaebecedeeef\
        f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
 
+/// Same as [`HEX_ENCODE`] but in uppercase
+const HEX_ENCODE_UPPER: [u8; 512] = {
+    let mut table = HEX_ENCODE;
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = table[i].to_ascii_uppercase();
+        i += 1;
+    }
+    table
+};
+
 // (L) least (M) more significant mibble masks
 const MN_MASK: i32 = 0xF0F0F0F0u32 as i32;
 const LN_MASK: i32 = 0x0F0F0F0F;
 const HEX_ENCODE_64LUT_1: i64 = i64::from_be_bytes(*b"fedcba98");
 const HEX_ENCODE_64LUT_0: i64 = i64::from_be_bytes(*b"76543210");
+const HEX_ENCODE_64LUT_UPPER_1: i64 = i64::from_be_bytes(*b"FEDCBA98");
 
-/// Allocates `Vec<u8>` of a given length with uninitialized data
-#[inline(always)]
-fn alloc(length: usize) -> Vec<u8> {
-    let mut v = Vec::with_capacity(length);
-    unsafe {
-        v.set_len(length);
+/// Letter case of the encoded hex chars
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    #[default]
+    Lower,
+    Upper,
+}
+
+/// Letter case accepted while decoding
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CaseRule {
+    /// Accepts any mix of cases
+    #[default]
+    Any,
+    /// Only lowercase letters, useful for canonical hashes
+    Lower,
+    /// Only uppercase letters
+    Upper,
+}
+
+impl CaseRule {
+    /// Decoding table of every char
+    #[inline(always)]
+    fn nibbles(self) -> &'static [u8; 256] {
+        match self {
+            CaseRule::Any => &HEX_NIBBLE_DECODE,
+            CaseRule::Lower => &HEX_NIBBLE_DECODE_LOWER,
+            CaseRule::Upper => &HEX_NIBBLE_DECODE_UPPER,
+        }
+    }
+
+    /// Decoding LUTs of the `[A-F]` and `[a-f]` ranges, a range not accepted
+    /// have only invalid entries
+    #[inline(always)]
+    fn lut(self) -> (i64, i64) {
+        match self {
+            CaseRule::Any => (HEX_DECODE_64LUT_AZ, HEX_DECODE_64LUT_AZ),
+            CaseRule::Lower => (0, HEX_DECODE_64LUT_AZ),
+            CaseRule::Upper => (HEX_DECODE_64LUT_AZ, 0),
+        }
     }
-    v
 }
 
-#[derive(Debug)]
+impl Case {
+    /// Encoding table of every byte
+    #[inline(always)]
+    fn table(self) -> &'static [u8; 512] {
+        match self {
+            Case::Lower => &HEX_ENCODE,
+            Case::Upper => &HEX_ENCODE_UPPER,
+        }
+    }
+
+    /// Encoding table of every nibble, as the high and low halves of a SIMD register
+    #[inline(always)]
+    fn lut(self) -> (i64, i64) {
+        match self {
+            Case::Lower => (HEX_ENCODE_64LUT_1, HEX_ENCODE_64LUT_0),
+            Case::Upper => (HEX_ENCODE_64LUT_UPPER_1, HEX_ENCODE_64LUT_0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DecodeError {
     OddLength,
     InvalidCharAt(usize),
     /// Offset was less than alignment (it needs to be at least equal or greater)
     BadOffset,
+    /// Offset is after the end of the input
+    OffsetPastEnd {
+        offset: usize,
+        len: usize,
+    },
+    /// Alignment isn't a power of two
+    InvalidAlignment(usize),
+    /// Decoded length isn't a multiple of the size of the requested type
+    LengthNotMultiple,
+    /// Output buffer can't hold all the decoded bytes
+    OutputTooSmall {
+        needed: usize,
+        got: usize,
+    },
+    /// Alignment header marker wasn't found
+    MissingHeader,
+    /// Alignment header have an invalid char at the given position
+    MalformedHeaderAt(usize),
+    /// Payload length doesn't match the one written in the alignment header
+    HeaderLengthMismatch {
+        expected: usize,
+        got: usize,
+    },
 }
 
 impl fmt::Display for DecodeError {
@@ -94,38 +241,157 @@ impl fmt::Display for DecodeError {
                 f,
                 "not enough offset was given, it needs to be equal or greater than alignment"
             ),
+            OffsetPastEnd { offset, len } => write!(
+                f,
+                "offset {} is past the end of the input of length {}",
+                offset, len
+            ),
+            InvalidAlignment(align) => write!(f, "alignment {} isn't a power of two", align),
+            LengthNotMultiple => write!(
+                f,
+                "decoded length isn't a multiple of the size of the requested type"
+            ),
+            OutputTooSmall { needed, got } => write!(
+                f,
+                "output buffer too small, needed {} bytes but got {}",
+                needed, got
+            ),
+            MissingHeader => write!(f, "alignment header not found"),
+            MalformedHeaderAt(pos) => write!(f, "malformed alignment header at {}", pos),
+            HeaderLengthMismatch { expected, got } => write!(
+                f,
+                "alignment header expected {} bytes but the payload has {}",
+                expected, got
+            ),
         }
     }
 }
 
 impl Error for DecodeError {}
 
+impl DecodeError {
+    /// Moves the position of an invalid char by `offset`, used when part of
+    /// the input is decoded on its own
+    #[inline(always)]
+    fn shifted(self, offset: usize) -> Self {
+        match self {
+            DecodeError::InvalidCharAt(i) => DecodeError::InvalidCharAt(i + offset),
+            e => e,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// Output buffer can't hold all the encoded chars
+    OutputTooSmall { needed: usize, got: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EncodeError::*;
+        match self {
+            OutputTooSmall { needed, got } => write!(
+                f,
+                "output buffer too small, needed {} bytes but got {}",
+                needed, got
+            ),
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+/// Checks if `output` is big enough to hold the decoded `input`,
+/// returns the number of bytes that will be decoded
+#[inline(always)]
+fn decoded_len(input: &[u8], output: &[u8]) -> Result<usize, DecodeError> {
+    use DecodeError::*;
+
+    let c = input.len();
+    if c & 1 != 0 {
+        Err(OddLength)?
+    }
+
+    let needed = c >> 1;
+    if output.len() < needed {
+        Err(OutputTooSmall {
+            needed,
+            got: output.len(),
+        })?
+    }
+
+    Ok(needed)
+}
+
+/// Checks if `output` is big enough to hold the encoded `input`,
+/// returns the number of chars that will be encoded
+#[inline(always)]
+fn encoded_len(input: &[u8], output: &[u8]) -> Result<usize, EncodeError> {
+    let needed = input.len() << 1;
+    if output.len() < needed {
+        Err(EncodeError::OutputTooSmall {
+            needed,
+            got: output.len(),
+        })?
+    }
+
+    Ok(needed)
+}
+
 /// Fast hex string decode. No error description is provided
+#[cfg(feature = "alloc")]
 #[no_mangle]
+#[allow(clippy::result_unit_err)]
 pub fn decode_noerr(input: &str) -> Result<Vec<u8>, ()> {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    if is_x86_feature_detected!("avx2") {
-        return unsafe { avx2::decode(input).map_err(|_| ()) };
-    } else if is_x86_feature_detected!("ssse3") {
-        return unsafe { sse2::decode(input).map_err(|_| ()) };
-    }
-
-    fallback::decode(input).map_err(|_| ())
+    decode_with_case(input, CaseRule::Any).map_err(|_| ())
 }
 
 /// Decodes an hex string with all error messages, useful when dealing with
 /// recoverable code logic or when a error message is required to facilitate
 /// user action.
+#[cfg(feature = "alloc")]
 #[no_mangle]
 pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    if is_x86_feature_detected!("avx2") {
-        return unsafe { avx2::decode(input) };
-    } else if is_x86_feature_detected!("ssse3") {
-        return unsafe { sse2::decode(input) };
-    }
+    decode_with_case(input, CaseRule::Any)
+}
+
+/// Decodes an hex string accepting only the letters allowed by `rule`,
+/// the first letter in the wrong case is reported as an invalid char.
+///
+/// ```rust
+/// use bintext::hex::{decode_with_case, CaseRule, DecodeError};
+///
+/// assert_eq!(decode_with_case("a1f7", CaseRule::Lower).unwrap(), b"\xa1\xf7");
+/// assert_eq!(decode_with_case("a1F7", CaseRule::Lower), Err(DecodeError::InvalidCharAt(2)));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_with_case(input: &str, rule: CaseRule) -> Result<Vec<u8>, DecodeError> {
+    Hex::new().rule(rule).decode(input)
+}
 
-    fallback::decode(input)
+/// Checks if `input` is a valid hex string without decoding it, the error
+/// is the same [`decode`] would return.
+///
+/// ```rust
+/// use bintext::hex::{validate, DecodeError};
+///
+/// assert_eq!(validate(b"a1f7d5e8"), Ok(()));
+/// assert_eq!(validate(b"a1f7d5e"), Err(DecodeError::OddLength));
+/// assert_eq!(validate(b"a1f7x5e8"), Err(DecodeError::InvalidCharAt(4)));
+/// ```
+pub fn validate(input: &[u8]) -> Result<(), DecodeError> {
+    Hex::new().validate(input)
+}
+
+/// Returns `true` when `input` is a valid hex string, see [`validate`]
+///
+/// ```rust
+/// assert!(bintext::hex::is_valid(b"a1F7"));
+/// assert!(!bintext::hex::is_valid(b"a1F7\n"));
+/// ```
+pub fn is_valid(input: &[u8]) -> bool {
+    validate(input).is_ok()
 }
 
 /// Decodes a hex str starting from `offset` with a given `align`ment.
@@ -133,7 +399,8 @@ pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
 /// The input str will no longer be a valid utf8 string, a byte slice
 /// will be returned upon success matching the alignment requirements
 ///
-/// **NOTE** `offset` must be greater or equal to `align`
+/// **NOTE** `offset` must be greater or equal to `align`, which must be a
+/// power of two, both are checked before anything is written
 ///
 /// ```rust
 /// // Padding of 8 (suppose it was read form a file)
@@ -142,87 +409,396 @@ pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
 /// unsafe {
 ///     // Decode with padding of 8 and alignment of 8
 ///     let slice = bintext::hex::decode_aligned(&mut hex, 8, 8).unwrap();
-///     // Data is aligned so you can safely do this:
-///     let slice: &[u64] = std::slice::from_raw_parts(
-///        slice.as_ptr() as *const _,
-///        slice.len() / std::mem::size_of::<u64>()
-///     );
+///     assert_eq!(slice.as_ptr().align_offset(8), 0);
 /// }
 /// ```
+///
+/// Use [`decode_aligned_as`] to view the decoded bytes as other types.
+///
+/// # Safety
+///
+/// The contents of `input` are overwritten with the decoded bytes, so it must
+/// not be used as a `str` afterwards.
 #[no_mangle]
 pub unsafe fn decode_aligned(
     input: &mut str,
     offset: usize,
     align: usize,
 ) -> Result<&mut [u8], DecodeError> {
-    use DecodeError::*;
+    Hex::new().decode_aligned(input, offset, align)
+}
 
-    // Safe only when if offset is greater or equal than the alignment requirement
-    if align > 1 && offset < align {
-        Err(BadOffset)?
-    }
+/// Same as [`decode_aligned`] but accepts any padding, even none at all.
+///
+/// Padding of `align - 1` is always enough, with less of it the first aligned
+/// position may be after `offset`, then the decoded bytes are shifted forward
+/// and must fit in `input`, otherwise [`DecodeError::BadOffset`] is returned.
+///
+/// ```rust
+/// // Padding of 7 is enough for an alignment of 8
+/// let mut hex = "-------a1f7d5e8d14f0f76".to_string();
+///
+/// unsafe {
+///     let slice = bintext::hex::decode_aligned_tight(&mut hex, 7, 8).unwrap();
+///     assert_eq!(slice, b"\xa1\xf7\xd5\xe8\xd1\x4f\x0f\x76");
+///     assert_eq!(slice.as_ptr().align_offset(8), 0);
+/// }
+/// ```
+///
+/// # Safety
+///
+/// The contents of `input` are overwritten with the decoded bytes, so it must
+/// not be used as a `str` afterwards.
+pub unsafe fn decode_aligned_tight(
+    input: &mut str,
+    offset: usize,
+    align: usize,
+) -> Result<&mut [u8], DecodeError> {
+    Hex::new().decode_aligned_tight(input, offset, align)
+}
 
-    let bytes = input.as_bytes_mut();
-    let len = bytes.len();
-    if (len - offset) & 1 != 0 {
+/// Decodes a hex str starting from `offset` as a slice of `T`, the
+/// alignment is the same of `T`.
+///
+/// Same as [`decode_aligned`] but also checks if the number of decoded
+/// bytes is a multiple of the size of `T`.
+///
+/// ```rust
+/// // Padding of 8 (suppose it was read form a file)
+/// let mut hex = "--------a1f7d5e8d14f0f76".to_string();
+///
+/// unsafe {
+///     let slice = bintext::hex::decode_aligned_as::<u64>(&mut hex, 8).unwrap();
+///     assert_eq!(slice, &[u64::from_le_bytes(*b"\xa1\xf7\xd5\xe8\xd1\x4f\x0f\x76")]);
+/// }
+/// ```
+///
+/// # Safety
+///
+/// The contents of `input` are overwritten with the decoded bytes, so it must
+/// not be used as a `str` afterwards.
+pub unsafe fn decode_aligned_as<T: Pod>(
+    input: &mut str,
+    offset: usize,
+) -> Result<&mut [T], DecodeError> {
+    use DecodeError::*;
+
+    let () = T::NOT_ZERO_SIZED;
+    let size = size_of::<T>();
+    let chars = input.len().saturating_sub(offset);
+    if chars & 1 != 0 {
         Err(OddLength)?
     }
+    if !(chars >> 1).is_multiple_of(size) {
+        Err(LengthNotMultiple)?
+    }
 
-    let a = bytes.as_ptr().align_offset(align);
-    let output = std::slice::from_raw_parts_mut(bytes.as_mut_ptr().add(a), (len - offset) / 2);
+    let bytes = decode_aligned(input, offset, align_of::<T>())?;
+    Ok(slice::from_raw_parts_mut(
+        bytes.as_mut_ptr() as *mut T,
+        bytes.len() / size,
+    ))
+}
 
-    let input = &bytes[offset..];
+/// Decodes `bytes` starting from `offset` in place with the `hex` engine,
+/// the decoded bytes are written at the first position of `bytes` that
+/// matches the `align`ment, when `tight` the padding can be smaller than `align`
+fn decode_in_place<'a>(
+    hex: &Hex,
+    bytes: &'a mut [u8],
+    offset: usize,
+    align: usize,
+    tight: bool,
+) -> Result<&'a mut [u8], DecodeError> {
+    let ptr = bytes.as_mut_ptr();
+    let (a, n) = aligned_layout(ptr, bytes.len(), offset, align, tight)?;
 
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    if is_x86_feature_detected!("avx2") {
-        return avx2::decode_noalloc(input, output).map(|_| output);
-    } else if is_x86_feature_detected!("ssse3") {
-        return sse2::decode_noalloc(input, output).map(|_| output);
+    // Input and output overlap, so both are only accessed by raw pointers
+    unsafe {
+        let functions = hex.functions();
+        if a <= offset {
+            (functions.decode_raw)(ptr.add(offset), n << 1, ptr.add(a), hex.rule)?;
+        } else {
+            decode_shifted(functions, hex.rule, ptr.add(offset), n, a - offset)?;
+        }
+        Ok(slice::from_raw_parts_mut(ptr.add(a), n))
     }
+}
+
+/// Decodes the `n` bytes of the chars at `src` into `src + d`.
+///
+/// Only the first `d` bytes would overwrite chars not read yet, so the
+/// other ones are decoded first, starting right where their chars start,
+/// then the first ones are decoded backward through a stack buffer, each
+/// block overwriting only chars of the blocks already decoded
+///
+/// # Safety
+///
+/// `src` must be valid for `n * 2` reads and `n + d` writes, `d` can't be
+/// greater than `n`
+unsafe fn decode_shifted(
+    functions: &dispatch::Functions,
+    rule: CaseRule,
+    src: *mut u8,
+    n: usize,
+    d: usize,
+) -> Result<(), DecodeError> {
+    // Nothing is written before checking the first chars, so any invalid
+    // char is reported at the same position of a forward decoding
+    (functions.validate_raw)(src, d << 1, rule)?;
+    (functions.decode_raw)(src.add(d << 1), (n - d) << 1, src.add(d << 1), rule)
+        .map_err(|e| e.shifted(d << 1))?;
 
-    fallback::decode_noalloc(input, output)?;
-    Ok(output)
+    let mut buffer = [0; 256];
+    let mut end = d;
+    while end > 0 {
+        let start = end.saturating_sub(buffer.len());
+        (functions.decode_raw)(
+            src.add(start << 1),
+            (end - start) << 1,
+            buffer.as_mut_ptr(),
+            rule,
+        )?;
+        ptr::copy_nonoverlapping(buffer.as_ptr(), src.add(d + start), end - start);
+        end = start;
+    }
+
+    Ok(())
 }
 
-/// Decodes an hex string without allocating any memory
-#[no_mangle]
-pub fn decode_noalloc(input: &str, output: &mut [u8]) -> Result<(), DecodeError> {
+/// Checks the layout of an in place decoding of the `len` chars at `ptr`,
+/// returns the position of the output and the number of decoded bytes
+fn aligned_layout(
+    ptr: *const u8,
+    len: usize,
+    offset: usize,
+    align: usize,
+    tight: bool,
+) -> Result<(usize, usize), DecodeError> {
     use DecodeError::*;
 
-    let c = input.len();
-    if c & 1 != 0 {
-        Err(OddLength)?
+    if !align.is_power_of_two() {
+        Err(InvalidAlignment(align))?
+    }
+
+    let chars = len
+        .checked_sub(offset)
+        .ok_or(OffsetPastEnd { offset, len })?;
+
+    // Safe only when if offset is greater or equal than the alignment requirement
+    if !tight && align > 1 && offset < align {
+        Err(BadOffset)?
     }
 
-    let input = input.as_bytes();
+    if chars & 1 != 0 {
+        Err(OddLength)?
+    }
 
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    if is_x86_feature_detected!("avx2") {
-        return unsafe { avx2::decode_noalloc(input, output) };
-    } else if is_x86_feature_detected!("ssse3") {
-        return unsafe { sse2::decode_noalloc(input, output) };
+    // The output must start inside the padding, so it's never after the input,
+    // unless it's shifted forward and still fits in the input
+    let n = chars >> 1;
+    let a = ptr.align_offset(align);
+    if a > offset && !(tight && a - offset <= n) {
+        Err(BadOffset)?
     }
 
-    fallback::decode_noalloc(input, output)
+    Ok((a, n))
+}
+
+/// Decodes an hex string without allocating any memory, `output` can be
+/// bigger than needed, the number of decoded bytes is returned
+#[no_mangle]
+pub fn decode_noalloc(input: &str, output: &mut [u8]) -> Result<usize, DecodeError> {
+    decode_slice(input.as_bytes(), output, CaseRule::Any)
+}
+
+/// Same as [`decode_noalloc`] but accepting only the letters allowed by `rule`
+pub fn decode_noalloc_with_case(
+    input: &str,
+    output: &mut [u8],
+    rule: CaseRule,
+) -> Result<usize, DecodeError> {
+    decode_slice(input.as_bytes(), output, rule)
+}
+
+/// Same as [`decode_noalloc_with_case`] but `input` doesn't need to be a valid `str`
+fn decode_slice(input: &[u8], output: &mut [u8], rule: CaseRule) -> Result<usize, DecodeError> {
+    Hex::new().rule(rule).decode_slice(input, output)
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 
+#[cfg(feature = "alloc")]
 #[no_mangle]
 pub fn encode(input: &[u8]) -> String {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    if is_x86_feature_detected!("avx2") {
-        return unsafe { avx2::encode(input) };
-    } else if is_x86_feature_detected!("ssse3") {
-        return unsafe { sse2::encode(input) };
+    encode_with_case(input, Case::Lower)
+}
+
+/// Encodes `input` using uppercase letters
+///
+/// ```rust
+/// assert_eq!(bintext::hex::encode_upper(b"\xa1\xf7"), "A1F7");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_upper(input: &[u8]) -> String {
+    encode_with_case(input, Case::Upper)
+}
+
+/// Encodes `input` into `output` without allocating any memory, `output` can be
+/// bigger than needed, the encoded part of it is returned
+///
+/// ```rust
+/// let mut buffer = [0; 16];
+/// let hex = bintext::hex::encode_noalloc(b"\xa1\xf7", &mut buffer).unwrap();
+/// assert_eq!(hex, "a1f7");
+/// ```
+pub fn encode_noalloc<'a>(input: &[u8], output: &'a mut [u8]) -> Result<&'a str, EncodeError> {
+    let n = encode_slice(input, output, Case::Lower)?;
+    Ok(unsafe { core::str::from_utf8_unchecked(&output[..n]) })
+}
+
+/// Appends the encoded `input` to `output`
+///
+/// ```rust
+/// let mut hex = "id=".to_string();
+/// bintext::hex::encode_into(&mut hex, b"\xa1\xf7");
+/// assert_eq!(hex, "id=a1f7");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_into(output: &mut String, input: &[u8]) {
+    encode_into_with_case(output, input, Case::Lower)
+}
+
+/// Appends the encoded `input` to `output` with the letters in the given `case`
+#[cfg(feature = "alloc")]
+pub fn encode_into_with_case(output: &mut String, input: &[u8], case: Case) {
+    Hex::new().case(case).encode_into(output, input)
+}
+
+/// Encodes `input` into `output` returning the number of encoded chars
+fn encode_slice(input: &[u8], output: &mut [u8], case: Case) -> Result<usize, EncodeError> {
+    Hex::new().case(case).encode_slice(input, output)
+}
+
+/// Encodes `input` with the letters in the given `case`
+#[cfg(feature = "alloc")]
+pub fn encode_with_case(input: &[u8], case: Case) -> String {
+    Hex::new().case(case).encode(input)
+}
+
+/// Default fill char of the padding written by [`encode_aligned`]
+pub const FILL: u8 = b'-';
+
+/// Padding needed by the payload written at `position`, the payload will start
+/// at the first multiple of `align` that leaves at least `align` and `min` chars
+#[inline(always)]
+fn aligned_padding(position: usize, align: usize, min: usize) -> usize {
+    if align <= 1 {
+        return min;
     }
 
-    fallback::encode(input)
+    let min = min.max(align);
+    min + (align - (position + min) % align) % align
+}
+
+/// Encodes `input` with a padding of `align` fill chars in front of it,
+/// the result can be decoded with [`decode_aligned`] using `align` as offset.
+///
+/// ```rust
+/// let mut hex = bintext::hex::encode_aligned(b"\xa1\xf7\xd5\xe8", 4);
+/// assert_eq!(hex, "----a1f7d5e8");
+///
+/// unsafe {
+///     let slice = bintext::hex::decode_aligned(&mut hex, 4, 4).unwrap();
+///     assert_eq!(slice, b"\xa1\xf7\xd5\xe8");
+/// }
+/// ```
+///
+/// # Panics
+///
+/// When `align` isn't a power of two
+#[cfg(feature = "alloc")]
+pub fn encode_aligned(input: &[u8], align: usize) -> String {
+    encode_aligned_with(input, align, FILL)
+}
+
+/// Same as [`encode_aligned`] but with a custom `fill` char
+///
+/// # Panics
+///
+/// When `align` isn't a power of two or `fill` isn't an ASCII char
+#[cfg(feature = "alloc")]
+pub fn encode_aligned_with(input: &[u8], align: usize, fill: u8) -> String {
+    let mut output = String::new();
+    encode_aligned_into(&mut output, input, align, fill);
+    output
+}
+
+/// Appends `input` encoded with the padding required by [`decode_aligned`],
+/// the padding is computed for the current length of `output`, so the payload
+/// always starts at a position multiple of `align` within it.
+///
+/// Returns the padding length, that is the offset to be used by
+/// [`decode_aligned`] when decoding from the current end of `output`.
+///
+/// ```rust
+/// let mut hex = "id=".to_string();
+/// let offset = bintext::hex::encode_aligned_into(&mut hex, b"\xa1\xf7", 4, b'-');
+/// assert_eq!(offset, 5);
+/// assert_eq!(hex, "id=-----a1f7");
+/// ```
+///
+/// # Panics
+///
+/// When `align` isn't a power of two or `fill` isn't an ASCII char
+#[cfg(feature = "alloc")]
+pub fn encode_aligned_into(output: &mut String, input: &[u8], align: usize, fill: u8) -> usize {
+    assert!(align.is_power_of_two(), "align must be a power of two");
+    assert!(fill.is_ascii(), "fill must be an ASCII char");
+
+    let padding = aligned_padding(output.len(), align, 0);
+    output.reserve(padding + (input.len() << 1));
+    output.extend(core::iter::repeat_n(fill as char, padding));
+    encode_into(output, input);
+    padding
+}
+
+/// Writes `input` encoded with the padding required by [`decode_aligned`],
+/// `position` is where `output` currently is, it's used to compute the
+/// padding just like [`encode_aligned_into`].
+///
+/// Returns the padding length, that is the offset to be used by
+/// [`decode_aligned`] when decoding from `position`.
+///
+/// # Panics
+///
+/// When `align` isn't a power of two or `fill` isn't an ASCII char
+pub fn encode_aligned_fmt<W: fmt::Write>(
+    output: &mut W,
+    position: usize,
+    input: &[u8],
+    align: usize,
+    fill: u8,
+) -> Result<usize, fmt::Error> {
+    assert!(align.is_power_of_two(), "align must be a power of two");
+    assert!(fill.is_ascii(), "fill must be an ASCII char");
+
+    let padding = aligned_padding(position, align, 0);
+    for _ in 0..padding {
+        output.write_char(fill as char)?;
+    }
+
+    // Encodes in small chunks to not allocate
+    let mut buffer = [0; 512];
+    for chunk in input.chunks(buffer.len() >> 1) {
+        output.write_str(encode_noalloc(chunk, &mut buffer).unwrap())?;
+    }
+    Ok(padding)
 }
 
 #[cfg(test)]
 mod tests_extra {
-    const SAMPLES_ALIGNED: [(&'static [u8], &'static str, usize, usize, usize); 5] = [
+    const SAMPLES_ALIGNED: [(&[u8], &str, usize, usize, usize); 5] = [
         (b"\x02\x03\x04\x05", "----02030405", 4, 4, 0),
         (b"\x02\x03\x04\x05", "#----02030405", 5, 4, 0),
         (b"\x02\x03\x04\x05", "#--02030405", 3, 2, 0),
@@ -239,4 +815,233 @@ mod tests_extra {
             assert_eq!(v.as_ptr().align_offset(*align), 0);
         }
     }
+
+    #[test]
+    fn decoding_aligned_as() {
+        use super::DecodeError::*;
+
+        let mut v = "----0100020003000400".to_string();
+        let v = unsafe { super::decode_aligned_as::<u32>(&mut v, 4).unwrap() };
+        assert_eq!(v.len(), 2);
+        assert_eq!(u32::from_le(v[0]), 0x20001);
+        assert_eq!(v.as_ptr().align_offset(4), 0);
+
+        let mut v = "--------0100020003000400".to_string();
+        let v = unsafe { super::decode_aligned_as::<[u16; 2]>(&mut v, 8).unwrap() };
+        assert_eq!(v.len(), 2);
+        assert_eq!(u16::from_le(v[1][1]), 4);
+
+        let mut v = "----010002".to_string();
+        let r = unsafe { super::decode_aligned_as::<u32>(&mut v, 4) };
+        assert_eq!(r.unwrap_err(), LengthNotMultiple);
+        assert_eq!(v, "----010002");
+
+        let mut v = "----0100020".to_string();
+        let r = unsafe { super::decode_aligned_as::<u32>(&mut v, 4) };
+        assert_eq!(r.unwrap_err(), OddLength);
+        assert_eq!(v, "----0100020");
+
+        let mut v = "--0100020003000400".to_string();
+        let r = unsafe { super::decode_aligned_as::<u64>(&mut v, 2) };
+        assert_eq!(r.unwrap_err(), BadOffset);
+    }
+
+    #[test]
+    fn decoding_aligned_layout() {
+        use super::DecodeError::*;
+
+        // Buffer with a known alignment
+        #[repr(align(64))]
+        struct Block([u8; 192]);
+
+        let hex = b"02030405";
+        let step = if cfg!(miri) { 7 } else { 1 };
+
+        for align in [1, 2, 4, 8, 16, 32, 64].iter() {
+            for misalign in (0..64).step_by(step) {
+                for offset in 0..=64 {
+                    let mut block = Block([super::FILL; 192]);
+                    let start = misalign + offset;
+                    block.0[start..start + hex.len()].copy_from_slice(hex);
+                    let v = &mut block.0[misalign..start + hex.len()];
+                    let v = core::str::from_utf8_mut(v).unwrap();
+                    let len = v.len();
+
+                    let r = unsafe { super::decode_aligned(v, len + 1 + offset, *align) };
+                    let expected = OffsetPastEnd {
+                        offset: len + 1 + offset,
+                        len,
+                    };
+                    assert_eq!(r.unwrap_err(), expected);
+
+                    let r = unsafe { super::decode_aligned(v, offset, *align) };
+                    if *align > 1 && offset < *align {
+                        assert_eq!(r.unwrap_err(), BadOffset);
+                        assert_eq!(&block.0[start..start + hex.len()], hex);
+                        continue;
+                    }
+
+                    let r = r.unwrap();
+                    assert_eq!(r, b"\x02\x03\x04\x05");
+                    assert_eq!(r.as_ptr().align_offset(*align), 0);
+                }
+            }
+        }
+
+        let mut v = "--------02030405".to_string();
+        for align in [0, 3, 6, 12, 63, usize::MAX].iter() {
+            let r = unsafe { super::decode_aligned(&mut v, 8, *align) };
+            assert_eq!(r.unwrap_err(), InvalidAlignment(*align));
+        }
+        assert_eq!(v, "--------02030405");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn decoding_aligned_tight() {
+        use super::DecodeError::*;
+
+        #[repr(align(64))]
+        struct Block([u8; 768]);
+
+        let data: Vec<u8> = (0..300).map(|i| (i * 37) as u8).collect();
+        let step = if cfg!(miri) { 11 } else { 1 };
+
+        for align in [1, 2, 4, 8, 16, 32, 64].iter() {
+            for misalign in (0..64).step_by(step) {
+                for offset in (0..=64).step_by(step) {
+                    for n in [0, 1, 3, 7, 40, 300].iter() {
+                        let hex = super::encode(&data[..*n]);
+                        let mut block = Block([super::FILL; 768]);
+                        let start = misalign + offset;
+                        let end = start + hex.len();
+                        block.0[start..end].copy_from_slice(hex.as_bytes());
+
+                        // Position of the first aligned byte of the input
+                        let a = (align - misalign % align) % align;
+                        let v = core::str::from_utf8_mut(&mut block.0[misalign..end]).unwrap();
+                        let r = unsafe { super::decode_aligned_tight(v, offset, *align) };
+                        if a > offset + n {
+                            assert_eq!(r.unwrap_err(), BadOffset);
+                            assert_eq!(&block.0[start..end], hex.as_bytes());
+                            continue;
+                        }
+
+                        let r = r.unwrap();
+                        assert_eq!(r, &data[..*n], "{} {} {} {}", align, misalign, offset, n);
+                        assert_eq!(r.as_ptr().align_offset(*align), 0);
+                    }
+                }
+            }
+        }
+
+        // Shifts longer than the stack buffer
+        let data: Vec<u8> = (0..2000).map(|i| (i * 37) as u8).collect();
+        let hex = super::encode(&data);
+        let mut v = vec![0; 3 * 4096];
+        let start = v.as_ptr().align_offset(4096) + 4096 - 1000;
+        v[start..start + hex.len()].copy_from_slice(hex.as_bytes());
+        let v = core::str::from_utf8_mut(&mut v[start..start + hex.len()]).unwrap();
+        let r = unsafe { super::decode_aligned_tight(v, 0, 4096).unwrap() };
+        assert_eq!(r, &data[..]);
+        assert_eq!(r.as_ptr().align_offset(4096), 0);
+
+        // The first invalid char is reported even if it's decoded last
+        #[repr(align(64))]
+        struct Small([u8; 192]);
+
+        for i in [0, 1, 15, 125, 126, 150].iter() {
+            let mut block = Small([b'0'; 192]);
+            block.0[1 + i] = b'x';
+            block.0[190] = b'x';
+            let v = core::str::from_utf8_mut(&mut block.0[1..191]).unwrap();
+            let r = unsafe { super::decode_aligned_tight(v, 0, 64) };
+            assert_eq!(r.unwrap_err(), InvalidCharAt(*i));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encoding_aligned() {
+        let input = b"\x02\x03\x04\x05";
+
+        assert_eq!(super::encode_aligned(input, 1), "02030405");
+        assert_eq!(super::encode_aligned(input, 8), "--------02030405");
+        assert_eq!(super::encode_aligned_with(input, 2, b' '), "  02030405");
+
+        for align in [1, 2, 4, 8, 16, 32, 64].iter() {
+            for position in 0..(align * 2) {
+                let mut v = "#".repeat(position);
+                let offset = super::encode_aligned_into(&mut v, input, *align, b'-');
+                assert!(offset >= *align || *align == 1);
+                assert_eq!(v.len() % align, 8 % align);
+
+                let mut w = "#".repeat(position);
+                let r = super::encode_aligned_fmt(&mut w, position, input, *align, b'-');
+                assert_eq!(r, Ok(offset));
+                assert_eq!(v, w);
+
+                let v = unsafe { super::decode_aligned(&mut v[position..], offset, *align) };
+                let v = v.unwrap();
+                assert_eq!(v, input);
+                assert_eq!(v.as_ptr().align_offset(*align), 0);
+            }
+        }
+
+        // Bigger than the chunks used by `encode_aligned_fmt`
+        let input: Vec<u8> = (0..1000).map(|i| (i * 37) as u8).collect();
+        let mut w = String::new();
+        super::encode_aligned_fmt(&mut w, 0, &input, 4, b'-').unwrap();
+        assert_eq!(w, super::encode_aligned(&input, 4));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[should_panic(expected = "align must be a power of two")]
+    fn encoding_aligned_not_power_of_two() {
+        super::encode_aligned(b"\x01\x02\x03", 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "align must be a power of two")]
+    fn encoding_aligned_zero() {
+        let mut w = String::new();
+        let _ = super::encode_aligned_fmt(&mut w, 0, b"\x01", 0, b'-');
+    }
+
+    #[test]
+    fn encoding_noalloc() {
+        use super::EncodeError::*;
+
+        let mut v = [0; 5];
+        assert_eq!(
+            super::encode_noalloc(b"\x02\x03\x04", &mut v),
+            Err(OutputTooSmall { needed: 6, got: 5 })
+        );
+        let mut v = [0; 8];
+        assert_eq!(super::encode_noalloc(b"\x02\x03\x04", &mut v), Ok("020304"));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encoding_into() {
+        let mut v = "#".to_string();
+        super::encode_into(&mut v, b"\x02\x03");
+        super::encode_into(&mut v, b"");
+        super::encode_into(&mut v, b"\xff");
+        assert_eq!(v, "#0203ff");
+    }
+
+    #[test]
+    fn decoding_noalloc() {
+        use super::DecodeError::*;
+
+        let mut v = [0; 4];
+        assert_eq!(
+            super::decode_noalloc("0203040506", &mut v),
+            Err(OutputTooSmall { needed: 5, got: 4 })
+        );
+        assert_eq!(super::decode_noalloc("020304", &mut v), Ok(3));
+        assert_eq!(v, [2, 3, 4, 0]);
+    }
 }
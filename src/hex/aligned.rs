@@ -0,0 +1,249 @@
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ops::Range;
+use core::slice;
+
+use alloc::vec::Vec;
+
+use super::{decode_in_place, DecodeError, Hex};
+use crate::Pod;
+
+/// Owned hex buffer decoded in place with a given alignment, a safe
+/// alternative to [`decode_aligned`](super::decode_aligned).
+///
+/// The original buffer is reused to store the decoded bytes, so it can't
+/// be turned back into a `String`.
+///
+/// ```rust
+/// use bintext::hex::AlignedHexBuf;
+///
+/// // Padding of 8 (suppose it was read form a file)
+/// let hex = "--------a1f7d5e8d14f0f76".to_string();
+///
+/// // Decode with padding of 8 and alignment of 8
+/// let buf = AlignedHexBuf::new(hex, 8, 8).unwrap();
+/// assert_eq!(buf.as_bytes(), b"\xa1\xf7\xd5\xe8\xd1\x4f\x0f\x76");
+///
+/// // Data is aligned so it can viewed as `u64`
+/// let slice: &[u64] = buf.as_u64().unwrap();
+/// assert_eq!(slice.len(), 1);
+/// ```
+pub struct AlignedHexBuf {
+    buffer: Vec<u8>,
+    range: Range<usize>,
+    align: usize,
+}
+
+macro_rules! view {
+    ($(#[$attr:meta])* $name:ident, $t:ty) => {
+        $(#[$attr])*
+        #[inline]
+        pub fn $name(&self) -> Option<&[$t]> {
+            self.view::<$t>()
+        }
+    };
+}
+
+impl AlignedHexBuf {
+    /// Decodes the hex `input` starting from `offset` with a given `align`ment,
+    /// the same requirements of [`decode_aligned`](super::decode_aligned) apply
+    pub fn new<T: Into<Vec<u8>>>(
+        input: T,
+        offset: usize,
+        align: usize,
+    ) -> Result<Self, DecodeError> {
+        let mut buffer = input.into();
+        let base = buffer.as_ptr() as usize;
+        let decoded = decode_in_place(&Hex::new(), &mut buffer[..], offset, align, false)?;
+        let start = decoded.as_ptr() as usize - base;
+        let range = start..start + decoded.len();
+
+        Ok(Self {
+            buffer,
+            range,
+            align,
+        })
+    }
+
+    /// Alignment of the decoded bytes
+    #[inline]
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Number of decoded bytes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Decoded bytes
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+
+    /// Decoded bytes
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer[self.range.clone()]
+    }
+
+    view!(
+        /// Views the decoded bytes as `u16`, `None` is returned when the
+        /// alignment or the length doesn't match
+        as_u16,
+        u16
+    );
+    view!(
+        /// Views the decoded bytes as `u32`, `None` is returned when the
+        /// alignment or the length doesn't match
+        as_u32,
+        u32
+    );
+    view!(
+        /// Views the decoded bytes as `u64`, `None` is returned when the
+        /// alignment or the length doesn't match
+        as_u64,
+        u64
+    );
+    view!(
+        /// Views the decoded bytes as `f32`, `None` is returned when the
+        /// alignment or the length doesn't match
+        as_f32,
+        f32
+    );
+    view!(
+        /// Views the decoded bytes as `f64`, `None` is returned when the
+        /// alignment or the length doesn't match
+        as_f64,
+        f64
+    );
+
+    /// Views the decoded bytes as `T`, `None` is returned when the
+    /// alignment or the length doesn't match
+    #[inline]
+    pub fn view<T: Pod>(&self) -> Option<&[T]> {
+        let bytes = self.as_bytes();
+        let () = T::NOT_ZERO_SIZED;
+        let size = size_of::<T>();
+        if align_of::<T>() > self.align || !bytes.len().is_multiple_of(size) {
+            return None;
+        }
+
+        // Safe because the alignment and length were checked and any bit pattern is a valid `T`
+        unsafe {
+            Some(slice::from_raw_parts(
+                bytes.as_ptr() as *const T,
+                bytes.len() / size,
+            ))
+        }
+    }
+
+    /// Mutable version of [`view`](Self::view)
+    #[inline]
+    pub fn view_mut<T: Pod>(&mut self) -> Option<&mut [T]> {
+        let align = self.align;
+        let bytes = self.as_bytes_mut();
+        let () = T::NOT_ZERO_SIZED;
+        let size = size_of::<T>();
+        if align_of::<T>() > align || !bytes.len().is_multiple_of(size) {
+            return None;
+        }
+
+        // Safe because the alignment and length were checked and any bit pattern is a valid `T`
+        unsafe {
+            Some(slice::from_raw_parts_mut(
+                bytes.as_mut_ptr() as *mut T,
+                bytes.len() / size,
+            ))
+        }
+    }
+}
+
+impl AsRef<[u8]> for AlignedHexBuf {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for AlignedHexBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedHexBuf")
+            .field("align", &self.align)
+            .field("bytes", &self.as_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoding() {
+        let buf = AlignedHexBuf::new("--------0100020003000400", 8, 8).unwrap();
+        assert_eq!(buf.len(), 8);
+        assert_eq!(buf.as_bytes(), b"\x01\x00\x02\x00\x03\x00\x04\x00");
+        assert_eq!(buf.as_bytes().as_ptr().align_offset(8), 0);
+        let v: Vec<u16> = buf
+            .as_u16()
+            .unwrap()
+            .iter()
+            .map(|v| u16::from_le(*v))
+            .collect();
+        assert_eq!(v, [1, 2, 3, 4]);
+        assert!(buf.as_u32().is_some());
+        assert!(buf.as_u64().is_some());
+        assert!(buf.as_f32().is_some());
+        assert!(buf.as_f64().is_some());
+
+        let buf = AlignedHexBuf::new(b"#----02030405".to_vec(), 5, 4).unwrap();
+        assert_eq!(buf.as_bytes(), b"\x02\x03\x04\x05");
+    }
+
+    #[test]
+    fn views() {
+        // Alignment is smaller than the type alignment
+        let buf = AlignedHexBuf::new("--0100020003000400", 2, 2).unwrap();
+        assert!(buf.as_u16().is_some());
+        assert!(buf.as_u32().is_none());
+        assert!(buf.as_f64().is_none());
+
+        // Length isn't a multiple of the type size
+        let buf = AlignedHexBuf::new("--------010002", 8, 8).unwrap();
+        assert!(buf.as_u16().is_none());
+        assert!(buf.as_u64().is_none());
+        assert_eq!(buf.view::<[u8; 3]>().unwrap(), &[[1, 0, 2]]);
+
+        let mut buf = AlignedHexBuf::new("--------0100020003000400", 8, 8).unwrap();
+        buf.view_mut::<u32>().unwrap()[1] = 0;
+        assert_eq!(buf.as_bytes(), b"\x01\x00\x02\x00\x00\x00\x00\x00");
+    }
+
+    #[test]
+    fn errors() {
+        use DecodeError::*;
+
+        assert_eq!(AlignedHexBuf::new("--01", 3, 1).unwrap_err(), OddLength);
+        assert_eq!(AlignedHexBuf::new("--0102", 2, 4).unwrap_err(), BadOffset);
+        assert_eq!(
+            AlignedHexBuf::new("--0102", 7, 1).unwrap_err(),
+            OffsetPastEnd { offset: 7, len: 6 }
+        );
+        assert_eq!(
+            AlignedHexBuf::new("------0102", 6, 3).unwrap_err(),
+            InvalidAlignment(3)
+        );
+        assert_eq!(
+            AlignedHexBuf::new("----01x2", 4, 4).unwrap_err(),
+            InvalidCharAt(2)
+        );
+    }
+}
@@ -1,12 +1,10 @@
-#![allow(dead_code)]
-
 #[cfg(target_arch = "x86")]
-use std::arch::x86::*;
+use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use core::arch::x86_64::*;
 
 use super::*;
-use std::ptr::copy_nonoverlapping;
+use core::ptr::copy_nonoverlapping;
 
 // TODO: These impl must be reviewed, since they are a lazy port
 // from the original SSE. The AVX2 set has some other instruction
@@ -14,50 +12,29 @@ use std::ptr::copy_nonoverlapping;
 
 ///////////////////////////////////////////////////////////////////////////////
 
-#[inline(always)]
-pub unsafe fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
-    use DecodeError::*;
-
-    // Input check
-    let c = input.len();
-    if c & 1 != 0 {
-        Err(OddLength)?
-    }
-
-    let mut v = alloc(c >> 1);
-    decode_noalloc(input.as_bytes(), &mut v[..])?;
-
-    Ok(v)
-}
-
-///////////////////////////////////////////////////////////////////////////////
-
 // Inverted to make error handle works
 const I: u8 = 0;
 const HEX_DECODE_64LUT_X30_1: i64 = i64::from_le_bytes([!0x8, !0x9, I, I, I, I, I, I]); // [0-9]
 const HEX_DECODE_64LUT_X30_0: i64 =
     i64::from_le_bytes([!0x0, !0x1, !0x2, !0x3, !0x4, !0x5, !0x6, !0x7]);
-const HEX_DECODE_64LUT_AZ: i64 = i64::from_le_bytes([I, !0xa, !0xb, !0xc, !0xd, !0xe, !0xf, I]); // [a-z] [A-Z]
 
-pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+/// Decodes the `len` chars at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `len` must
+/// be even, `src` must be valid for `len` reads and `dst` for `len / 2` writes,
+/// they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    len: usize,
+    dst: *mut u8,
+    rule: CaseRule,
+) -> Result<(), DecodeError> {
     use DecodeError::*;
 
     // Constants
-    let lutx3 = _mm256_set_epi64x(
-        HEX_DECODE_64LUT_X30_1,
-        HEX_DECODE_64LUT_X30_0,
-        HEX_DECODE_64LUT_X30_1,
-        HEX_DECODE_64LUT_X30_0,
-    );
-    let lutx4and6 = _mm256_set_epi64x(0, HEX_DECODE_64LUT_AZ, 0, HEX_DECODE_64LUT_AZ);
-
-    let on = _mm256_set1_epi8(-1);
-    let x30 = _mm256_set1_epi8(0x30u8 as i8);
-    let x3f = _mm256_set1_epi8(0x3fu8 as i8);
-    let x40 = _mm256_set1_epi8(0x40u8 as i8);
-    let x4f = _mm256_set1_epi8(0x4fu8 as i8);
-    let x5f = _mm256_set1_epi8(0x5fu8 as i8);
-    let x60 = _mm256_set1_epi8(0x60u8 as i8);
+    let lookup = Lookup::new(rule);
 
     let m = _mm256_set1_epi16(0x00FFu16 as i16);
     let idec = _mm256_set_epi64x(
@@ -69,50 +46,14 @@ pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), Deco
     let tmpsll = _mm_set1_epi64x(12);
     let filled = _mm256_set1_epi64x(-1);
 
-    // Input pointers
-    let mut p = input.as_ptr() as *const i8;
-    let p_end = p.add(input.len());
-
-    // Allocate chunks of 8 bytes with alignment of 8
-    let mut b = output.as_mut_ptr();
-
-    // Main loop loop
-    while p.offset(31) < p_end {
+    // Decodes the 32 chars at `p` into the 16 bytes at `b`, returns the mask
+    // of the invalid chars, nothing is written when there is any
+    let block = |p: *const u8, b: *mut u8| {
         let slice = _mm256_loadu_si256(p as *const __m256i);
 
-        // Calculates LUT range masks
-        let mx6 = _mm256_cmpgt_epi8(slice, x5f);
-        let mx4 = _mm256_andnot_si256(_mm256_cmpgt_epi8(slice, x4f), _mm256_cmpgt_epi8(slice, x3f));
-        let mx3 = {
-            let temp = _mm256_cmpgt_epi8(slice, x3f); // x < 0x40 == !(x > 0x39)
-            _mm256_andnot_si256(temp, on)
-        };
-
-        // LUT indexes
-        let ix3 = _mm256_sub_epi8(slice, x30);
-        let ix4 = _mm256_sub_epi8(slice, x40);
-        let ix6 = _mm256_sub_epi8(slice, x60);
-
-        // LUT sample
-        let vx3 = _mm256_shuffle_epi8(lutx3, ix3);
-        let vx4 = _mm256_shuffle_epi8(lutx4and6, ix4);
-        let vx6 = _mm256_shuffle_epi8(lutx4and6, ix6);
-
-        // Aggregate results
-        let dec = _mm256_blendv_epi8(
-            _mm256_blendv_epi8(_mm256_and_si256(vx3, mx3), vx4, mx4),
-            vx6,
-            mx6,
-        );
-
-        // NOTE: To make the error handling possible I inverted all
-        // operations and constants of the algorithm, this way when
-        // `_mm_shuffle_epi8` recives an out of bounds index it will
-        // return 0 which is not ok
-        let ok = _mm256_movemask_epi8(dec) as u32;
+        let (dec, ok) = lookup.apply(slice);
         if ok != 0xffffffff {
-            // TODO: Error index
-            Err(InvalidCharAt(0))?
+            return !ok;
         }
 
         let dec = {
@@ -131,29 +72,157 @@ pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), Deco
         let ptr = &dec as *const _ as *const u8;
         copy_nonoverlapping(ptr.add(8), b, 8);
         copy_nonoverlapping(ptr.add(24), b.add(8), 8);
+        0
+    };
+
+    // Main loop loop
+    let mut i = 0;
+    while len - i >= 32 {
+        let bad = block(src.add(i), dst.add(i >> 1));
+        if bad != 0 {
+            // First set bit is the first invalid char of the slice
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
+        }
+
+        i += 32;
+    }
 
-        p = p.add(32);
-        b = b.add(16);
+    if i == len {
+        return Ok(());
     }
 
-    // TODO: Should I add the SSE impl to process the remaining bytes ?
+    // The remaining chars can't be read again by an overlapping load, since
+    // in place decoding may have already overwritten them, so they cascade
+    // to SSSE3
+    sse2::decode_raw(src.add(i), len - i, dst.add(i >> 1), rule).map_err(|e| e.shifted(i))
+}
 
-    // Handle the remaining of bytes
-    while p < p_end {
-        let msn = *HEX_NIBBLE_DECODE.get_unchecked(*p as usize);
-        if msn > 0xf {
-            Err(InvalidCharAt(0))?
+/// LUT range checks shared by decoding and validation
+struct Lookup {
+    lutx3: __m256i,
+    lutx4: __m256i,
+    lutx6: __m256i,
+    x2f: __m256i,
+    x30: __m256i,
+    x3f: __m256i,
+    x40: __m256i,
+    x4f: __m256i,
+    x5f: __m256i,
+    x60: __m256i,
+    x6f: __m256i,
+}
+
+impl Lookup {
+    #[inline(always)]
+    unsafe fn new(rule: CaseRule) -> Self {
+        let (az4, az6) = rule.lut();
+        Self {
+            lutx3: _mm256_set_epi64x(
+                HEX_DECODE_64LUT_X30_1,
+                HEX_DECODE_64LUT_X30_0,
+                HEX_DECODE_64LUT_X30_1,
+                HEX_DECODE_64LUT_X30_0,
+            ),
+            lutx4: _mm256_set_epi64x(0, az4, 0, az4),
+            lutx6: _mm256_set_epi64x(0, az6, 0, az6),
+            x2f: _mm256_set1_epi8(0x2fu8 as i8),
+            x30: _mm256_set1_epi8(0x30u8 as i8),
+            x3f: _mm256_set1_epi8(0x3fu8 as i8),
+            x40: _mm256_set1_epi8(0x40u8 as i8),
+            x4f: _mm256_set1_epi8(0x4fu8 as i8),
+            x5f: _mm256_set1_epi8(0x5fu8 as i8),
+            x60: _mm256_set1_epi8(0x60u8 as i8),
+            x6f: _mm256_set1_epi8(0x6fu8 as i8),
         }
-        p = p.add(1);
+    }
 
-        let lsn = *HEX_NIBBLE_DECODE.get_unchecked(*p as usize);
-        if lsn > 0xf {
-            Err(InvalidCharAt(0))?
+    /// Returns the inverted nibbles of `slice` and the mask of its valid chars
+    #[inline(always)]
+    unsafe fn apply(&self, slice: __m256i) -> (__m256i, u32) {
+        // Calculates LUT range masks
+        // Without the upper bounds the shuffles would wrap the letters
+        // past 'f' or 'F' to the valid ones
+        let mx6 = _mm256_andnot_si256(
+            _mm256_cmpgt_epi8(slice, self.x6f),
+            _mm256_cmpgt_epi8(slice, self.x5f),
+        );
+        let mx4 = _mm256_andnot_si256(
+            _mm256_cmpgt_epi8(slice, self.x4f),
+            _mm256_cmpgt_epi8(slice, self.x3f),
+        );
+        // x < 0x40 == !(x > 0x3f), non ASCII chars are negative, without
+        // the lower bound some of them would index the digits
+        let mx3 = _mm256_andnot_si256(
+            _mm256_cmpgt_epi8(slice, self.x3f),
+            _mm256_cmpgt_epi8(slice, self.x2f),
+        );
+
+        // LUT indexes
+        let ix3 = _mm256_sub_epi8(slice, self.x30);
+        let ix4 = _mm256_sub_epi8(slice, self.x40);
+        let ix6 = _mm256_sub_epi8(slice, self.x60);
+
+        // LUT sample
+        let vx3 = _mm256_shuffle_epi8(self.lutx3, ix3);
+        let vx4 = _mm256_shuffle_epi8(self.lutx4, ix4);
+        let vx6 = _mm256_shuffle_epi8(self.lutx6, ix6);
+
+        // Aggregate results
+        let dec = _mm256_blendv_epi8(
+            _mm256_blendv_epi8(_mm256_and_si256(vx3, mx3), vx4, mx4),
+            vx6,
+            mx6,
+        );
+
+        // NOTE: To make the error handling possible I inverted all
+        // operations and constants of the algorithm, this way when
+        // `_mm_shuffle_epi8` recives an out of bounds index it will
+        // return 0 which is not ok
+        (dec, _mm256_movemask_epi8(dec) as u32)
+    }
+}
+
+/// Checks if the `len` chars at `src` are valid hex, the length isn't checked
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads
+pub unsafe fn validate_raw(src: *const u8, len: usize, rule: CaseRule) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let lookup = Lookup::new(rule);
+
+    // Mask of the invalid chars of the 32 at `p`
+    let check = |p: *const u8| {
+        let (_, ok) = lookup.apply(_mm256_loadu_si256(p as *const __m256i));
+        !ok
+    };
+
+    let mut i = 0;
+    while len - i >= 32 {
+        let bad = check(src.add(i));
+        if bad != 0 {
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
         }
-        p = p.add(1);
 
-        *b = (msn << 4) | lsn;
-        b = b.add(1);
+        i += 32;
+    }
+
+    if i == len {
+        return Ok(());
+    }
+
+    if len < 32 {
+        return sse2::validate_raw(src, len, rule);
+    }
+
+    // Overlaps the last full block, the chars checked again are valid
+    // so the first invalid one is still reported
+    let i = len - 32;
+    let bad = check(src.add(i));
+    if bad != 0 {
+        Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
     }
 
     Ok(())
@@ -161,29 +230,22 @@ pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), Deco
 
 ///////////////////////////////////////////////////////////////////////////////
 
-#[inline(always)]
-pub unsafe fn encode(input: &[u8]) -> String {
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads and `dst` for `len * 2` writes, without overlapping
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, case: Case) {
     // Constants
-    let lut = _mm256_set_epi64x(
-        HEX_ENCODE_64LUT_1,
-        HEX_ENCODE_64LUT_0,
-        HEX_ENCODE_64LUT_1,
-        HEX_ENCODE_64LUT_0,
-    );
+    let (lut1, lut0) = case.lut();
+    let lut = _mm256_set_epi64x(lut1, lut0, lut1, lut0);
     let umask = _mm256_set1_epi32(MN_MASK);
     let lmask = _mm256_set1_epi32(LN_MASK);
     let srl = _mm_set1_epi64x(4);
 
-    let c = input.len();
-    let mut p = input.as_ptr() as *const i8;
-    let p_end = p.add(c);
-
-    // Allocate chunks of 8 bytes with alignment of 8
-    // * NOTE: each byte need two other bytes, hence shift left 1 bits
-    let mut v = alloc(c << 1);
-    let mut b = v.as_mut_ptr();
-
-    while p.offset(31) < p_end {
+    // Encodes the 32 bytes at `p` into the 64 chars at `b`
+    let block = |p: *const u8, b: *mut u8| {
         // * NOTE: no measurable change when taking 2 u64 at the time instead of 16 u8
         // but this will required forcing the input to be 8 bytes alingned, witch is
         // very complex to do
@@ -210,34 +272,26 @@ pub unsafe fn encode(input: &[u8]) -> String {
 
         copy_nonoverlapping(ptr0.add(16), b.add(32), 16);
         copy_nonoverlapping(ptr1.add(16), b.add(48), 16);
+    };
 
-        p = p.add(32);
-        b = b.add(64);
+    let mut i = 0;
+    while len - i >= 32 {
+        block(src.add(i), dst.add(i << 1));
+        i += 32;
     }
 
-    // TODO: Should I add the SSE impl to process the remaining bytes ?
-
-    // loop through the remaining 31 or less bytes
-
-    while p < p_end {
-        let j = ((*p as u8) as usize) << 1;
-        *b = *HEX_ENCODE.get_unchecked(j);
-        *b.add(1) = *HEX_ENCODE.get_unchecked(j | 1);
-
-        p = p.add(1);
-        b = b.add(2);
+    if i == len {
+        return;
     }
 
-    String::from_utf8_unchecked(v)
-}
-
-#[inline(always)]
-pub fn meet_requirements() -> bool {
-    if is_x86_feature_detected!("avx2") {
-        return true;
+    if len >= 32 {
+        // Overlaps the last full block, its chars are written again
+        let i = len - 32;
+        block(src.add(i), dst.add(i << 1));
+    } else {
+        // Shorter than a block
+        sse2::encode_raw(src, len, dst, case);
     }
-
-    return false;
 }
 
-crate::tests_hex!(super::encode, super::decode, super::meet_requirements);
+crate::tests_hex!(crate::hex::Backend::Avx2);
@@ -0,0 +1,162 @@
+//! AVX512BW and AVX512VBMI backend, `vpermb` does the whole nibble lookup
+//! and masked loads and stores handle the tail without a scalar loop
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use super::*;
+
+///////////////////////////////////////////////////////////////////////////////
+
+/// Decodes the `len` chars at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `len` must
+/// be even, `src` must be valid for `len` reads and `dst` for `len / 2` writes,
+/// they can only overlap when `dst` isn't after `src`
+#[target_feature(enable = "avx512f,avx512bw,avx512vbmi")]
+pub unsafe fn decode_raw(
+    src: *const u8,
+    len: usize,
+    dst: *mut u8,
+    rule: CaseRule,
+) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    // The ASCII half of the nibble table, invalid entries have the high bit set
+    let nibbles = rule.nibbles().as_ptr();
+    let lut0 = _mm512_loadu_si512(nibbles as *const __m512i);
+    let lut1 = _mm512_loadu_si512(nibbles.add(64) as *const __m512i);
+
+    // Most significant nibbles are multiplied by 16 then added to the least ones
+    let join = _mm512_set1_epi16(0x0110);
+
+    let mut i = 0;
+    let mut j = 0;
+    while i < len {
+        // Masked lanes are never read, so the tail can't fault
+        let remaining = len - i;
+        let (load, store) = if remaining >= 64 {
+            (!0u64, !0u32)
+        } else {
+            ((1u64 << remaining) - 1, (1u32 << (remaining >> 1)) - 1)
+        };
+
+        let slice = _mm512_maskz_loadu_epi8(load, src.add(i) as *const i8);
+
+        // Non ASCII chars index the table with 7 bits too, so they are
+        // caught by their own high bit
+        let dec = _mm512_permutex2var_epi8(lut0, slice, lut1);
+        let bad = _mm512_movepi8_mask(_mm512_or_si512(dec, slice)) & load;
+        if bad != 0 {
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
+        }
+
+        let dec = _mm512_maddubs_epi16(dec, join);
+        _mm512_mask_cvtepi16_storeu_epi8(dst.add(j) as *mut i8, store, dec);
+
+        i += 64;
+        j += 32;
+    }
+
+    Ok(())
+}
+
+/// Checks if the `len` chars at `src` are valid hex, the length isn't checked
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads
+#[target_feature(enable = "avx512f,avx512bw,avx512vbmi")]
+pub unsafe fn validate_raw(src: *const u8, len: usize, rule: CaseRule) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let nibbles = rule.nibbles().as_ptr();
+    let lut0 = _mm512_loadu_si512(nibbles as *const __m512i);
+    let lut1 = _mm512_loadu_si512(nibbles.add(64) as *const __m512i);
+
+    let mut i = 0;
+    while i < len {
+        let remaining = len - i;
+        let load = if remaining >= 64 {
+            !0u64
+        } else {
+            (1u64 << remaining) - 1
+        };
+
+        let slice = _mm512_maskz_loadu_epi8(load, src.add(i) as *const i8);
+        let dec = _mm512_permutex2var_epi8(lut0, slice, lut1);
+        let bad = _mm512_movepi8_mask(_mm512_or_si512(dec, slice)) & load;
+        if bad != 0 {
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
+        }
+
+        i += 64;
+    }
+
+    Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+/// Encoding table of every nibble, only the first 16 entries are used
+const fn digits(letters: &[u8; 16]) -> [u8; 64] {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 16 {
+        table[i] = letters[i];
+        i += 1;
+    }
+    table
+}
+
+const HEX_DIGITS: [u8; 64] = digits(b"0123456789abcdef");
+const HEX_DIGITS_UPPER: [u8; 64] = digits(b"0123456789ABCDEF");
+
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads and `dst` for `len * 2` writes, without overlapping
+#[target_feature(enable = "avx512f,avx512bw,avx512vbmi")]
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, case: Case) {
+    let digits = match case {
+        Case::Lower => &HEX_DIGITS,
+        Case::Upper => &HEX_DIGITS_UPPER,
+    };
+    let lut = _mm512_loadu_si512(digits.as_ptr() as *const __m512i);
+    let lmask = _mm512_set1_epi16(0x000f);
+
+    // Each byte gets its own 16 bits lane, the most significant nibble
+    // goes in the first byte of the lane
+    let encode = |slice: __m256i| {
+        let slice = _mm512_cvtepu8_epi16(slice);
+        let mnibble = _mm512_srli_epi16::<4>(slice);
+        let lnibble = _mm512_slli_epi16::<8>(_mm512_and_si512(slice, lmask));
+        _mm512_permutexvar_epi8(_mm512_or_si512(mnibble, lnibble), lut)
+    };
+
+    let mut i = 0;
+    while len - i >= 32 {
+        let slice = _mm256_loadu_si256(src.add(i) as *const __m256i);
+        _mm512_storeu_si512(dst.add(i << 1) as *mut __m512i, encode(slice));
+        i += 32;
+    }
+
+    // Masked lanes are never read nor written
+    let remaining = len - i;
+    if remaining > 0 {
+        let load = (1u64 << remaining) - 1;
+        let store = (1u64 << (remaining << 1)) - 1;
+        let slice = _mm512_maskz_loadu_epi8(load, src.add(i) as *const i8);
+        let hex = encode(_mm512_castsi512_si256(slice));
+        _mm512_mask_storeu_epi8(dst.add(i << 1) as *mut i8, store, hex);
+    }
+}
+
+crate::tests_hex!(crate::hex::Backend::Avx512, optional);
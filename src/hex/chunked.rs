@@ -0,0 +1,171 @@
+//! Resumable decoding of hex split in arbitrary chunks
+
+use super::{decode_slice, CaseRule, DecodeError};
+
+/// Decodes hex fed in chunks of any length, a byte pair split between two
+/// chunks is kept until the next one arrives.
+///
+/// ```rust
+/// let mut decoder = bintext::hex::ChunkedDecoder::new();
+/// let mut output = [0; 4];
+///
+/// let n = decoder.feed(b"a1f", &mut output).unwrap();
+/// let m = decoder.feed(b"7d5e8", &mut output[n..]).unwrap();
+/// decoder.finish().unwrap();
+/// assert_eq!(&output[..n + m], b"\xa1\xf7\xd5\xe8");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ChunkedDecoder {
+    /// Last char of an odd chunk, decoded along with the next one
+    nibble: Option<u8>,
+    /// Number of chars decoded so far
+    position: usize,
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of chars decoded so far, the pending nibble isn't included
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Tells if the last chunk ended in the middle of a byte pair
+    pub fn is_pending(&self) -> bool {
+        self.nibble.is_some()
+    }
+
+    /// Decodes `chunk` into `output` returning the number of decoded bytes,
+    /// `output` must hold every complete byte pair including the pending one.
+    ///
+    /// Error positions are counted from the first chunk, the decoder state
+    /// is left untouched when an error is returned.
+    pub fn feed(&mut self, chunk: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        use DecodeError::*;
+
+        let (first, input) = match (self.nibble, chunk.split_first()) {
+            (Some(c), Some((d, input))) => (Some([c, *d]), input),
+            _ => (None, chunk),
+        };
+
+        let even = input.len() & !1;
+        let needed = first.is_some() as usize + (even >> 1);
+        if output.len() < needed {
+            Err(OutputTooSmall {
+                needed,
+                got: output.len(),
+            })?
+        }
+
+        let mut position = self.position;
+        let mut j = 0;
+        if let Some(pair) = first {
+            decode_slice(&pair, &mut output[..1], CaseRule::Any)
+                .map_err(|e| e.shifted(position))?;
+            position += 2;
+            j = 1;
+        }
+
+        decode_slice(&input[..even], &mut output[j..needed], CaseRule::Any)
+            .map_err(|e| e.shifted(position))?;
+
+        if !chunk.is_empty() {
+            self.nibble = input.get(even).copied();
+        }
+        self.position = position + even;
+
+        Ok(needed)
+    }
+
+    /// Ends the decoding, fails with [`DecodeError::OddLength`] if a byte pair
+    /// is still pending. The decoder is reset so it can be used again.
+    pub fn finish(&mut self) -> Result<(), DecodeError> {
+        let nibble = self.nibble;
+        *self = Self::default();
+
+        if nibble.is_some() {
+            Err(DecodeError::OddLength)?
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::hex::encode;
+
+    fn data() -> Vec<u8> {
+        (0..300).map(|i| (i * 37) as u8).collect()
+    }
+
+    #[test]
+    fn decoding() {
+        let expected = data();
+        let input = encode(&expected);
+
+        for step in 1..70 {
+            let mut decoder = ChunkedDecoder::new();
+            let mut v = vec![0; expected.len()];
+            let mut j = 0;
+            for chunk in input.as_bytes().chunks(step) {
+                j += decoder.feed(chunk, &mut v[j..]).unwrap();
+            }
+            decoder.feed(b"", &mut []).unwrap();
+            decoder.finish().unwrap();
+            assert_eq!(j, expected.len());
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn decoding_errors() {
+        use DecodeError::*;
+
+        let input = encode(&data());
+        for i in [0, 1, 2, 31, 32, 33, 599].iter() {
+            for step in [1, 3, 7, 64].iter() {
+                let mut v = input.clone();
+                v.replace_range(*i..(*i + 1), "g");
+
+                let mut decoder = ChunkedDecoder::new();
+                let mut output = vec![0; 300];
+                let mut j = 0;
+                let mut error = None;
+                for chunk in v.as_bytes().chunks(*step) {
+                    match decoder.feed(chunk, &mut output[j..]) {
+                        Ok(n) => j += n,
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        }
+                    }
+                }
+                assert_eq!(error, Some(InvalidCharAt(*i)), "{} {}", i, step);
+            }
+        }
+
+        // Odd length only at the end
+        let mut decoder = ChunkedDecoder::new();
+        let mut output = [0; 2];
+        assert_eq!(decoder.feed(b"a1f", &mut output), Ok(1));
+        assert!(decoder.is_pending());
+        assert_eq!(decoder.finish(), Err(OddLength));
+        assert!(!decoder.is_pending());
+
+        // Output must hold the pending pair too
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(decoder.feed(b"a", &mut []), Ok(0));
+        assert_eq!(
+            decoder.feed(b"1f7", &mut output[..1]),
+            Err(OutputTooSmall { needed: 2, got: 1 })
+        );
+        assert_eq!(decoder.feed(b"1f7", &mut output), Ok(2));
+        assert_eq!(output, *b"\xa1\xf7");
+        assert_eq!(decoder.position(), 4);
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+}
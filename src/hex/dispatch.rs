@@ -0,0 +1,374 @@
+//! Hex functions of each backend, the table of the selected one is cached
+
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::base64;
+use crate::dispatch::{Backend, Selected, UnsupportedBackend};
+
+use super::{
+    decoded_len, encoded_len, fallback, swar, Case, CaseRule, DecodeError, EncodeError, SkipSet,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::{avx2, avx512, sse2};
+
+impl Backend {
+    /// Hex table of a supported backend
+    pub(crate) fn functions(self) -> &'static Functions {
+        match self {
+            Backend::Swar => &SWAR,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Ssse3 => &SSE2,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => &AVX2,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx512 => &AVX512,
+            _ => &FALLBACK,
+        }
+    }
+}
+
+/// Forces the `backend` used to encode and decode, useful to reproduce issues
+/// of other CPUs. Unlike the [`BACKEND_VAR`](crate::BACKEND_VAR) cap it's
+/// also honored with the `sse_ubiquitous` feature, which only changes the
+/// backend of the free functions.
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use bintext::hex::{with_backend, Backend};
+///
+/// let hex = with_backend(Backend::Fallback).unwrap();
+/// assert_eq!(hex.decode("a1f7").unwrap(), b"\xa1\xf7");
+/// assert_eq!(hex.encode(b"\xa1\xf7"), "a1f7");
+///
+/// let config = hex.base64(bintext::base64::STANDARD).unwrap();
+/// assert_eq!(bintext::base64::encode(b"\xa1\xf7", config), "ofc=");
+/// # }
+/// ```
+pub fn with_backend(backend: Backend) -> Result<WithBackend, UnsupportedBackend> {
+    if !backend.is_supported() {
+        Err(UnsupportedBackend(backend))?
+    }
+
+    Ok(WithBackend {
+        functions: backend.functions(),
+    })
+}
+
+/// Encodes and decodes with the backend given to [`with_backend`]
+#[derive(Clone, Copy)]
+pub struct WithBackend {
+    functions: &'static Functions,
+}
+
+impl WithBackend {
+    pub fn backend(&self) -> Backend {
+        self.functions.backend
+    }
+
+    /// Same base64 `config` using this backend, see
+    /// [`base64::Config::backend`](crate::base64::Config::backend)
+    pub fn base64(&self, config: base64::Config) -> Result<base64::Config, UnsupportedBackend> {
+        config.backend(self.backend())
+    }
+
+    /// Same as [`hex::decode`](super::decode)
+    #[cfg(feature = "alloc")]
+    pub fn decode(&self, input: &str) -> Result<Vec<u8>, DecodeError> {
+        self.functions.decode(input.as_bytes(), CaseRule::Any)
+    }
+
+    /// Same as [`hex::decode_with_case`](super::decode_with_case)
+    #[cfg(feature = "alloc")]
+    pub fn decode_with_case(&self, input: &str, rule: CaseRule) -> Result<Vec<u8>, DecodeError> {
+        self.functions.decode(input.as_bytes(), rule)
+    }
+
+    /// Same as [`hex::decode_noalloc`](super::decode_noalloc)
+    pub fn decode_noalloc(&self, input: &str, output: &mut [u8]) -> Result<usize, DecodeError> {
+        self.functions
+            .decode_slice(input.as_bytes(), output, CaseRule::Any)
+    }
+
+    /// Same as [`hex::validate`](super::validate)
+    pub fn validate(&self, input: &[u8]) -> Result<(), DecodeError> {
+        self.functions.validate(input, CaseRule::Any)
+    }
+
+    /// Same as [`hex::encode`](super::encode)
+    #[cfg(feature = "alloc")]
+    pub fn encode(&self, input: &[u8]) -> String {
+        self.encode_with_case(input, Case::Lower)
+    }
+
+    /// Same as [`hex::encode_with_case`](super::encode_with_case)
+    #[cfg(feature = "alloc")]
+    pub fn encode_with_case(&self, input: &[u8], case: Case) -> String {
+        let mut output = String::new();
+        self.functions.encode_into(&mut output, input, case);
+        output
+    }
+
+    /// Same as [`hex::encode_noalloc`](super::encode_noalloc)
+    pub fn encode_noalloc<'a>(
+        &self,
+        input: &[u8],
+        output: &'a mut [u8],
+    ) -> Result<&'a str, EncodeError> {
+        let n = self.functions.encode_slice(input, output, Case::Lower)?;
+        Ok(unsafe { core::str::from_utf8_unchecked(&output[..n]) })
+    }
+
+    /// Same as [`hex::encode_into`](super::encode_into)
+    #[cfg(feature = "alloc")]
+    pub fn encode_into(&self, output: &mut String, input: &[u8]) {
+        self.functions.encode_into(output, input, Case::Lower)
+    }
+}
+
+impl fmt::Debug for WithBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WithBackend").field(&self.backend()).finish()
+    }
+}
+
+type DecodeRaw = unsafe fn(*const u8, usize, *mut u8, CaseRule) -> Result<(), DecodeError>;
+type ValidateRaw = unsafe fn(*const u8, usize, CaseRule) -> Result<(), DecodeError>;
+type EncodeRaw = unsafe fn(*const u8, usize, *mut u8, Case);
+type Compact = unsafe fn(&[u8], &SkipSet, &mut [u8]) -> Result<usize, DecodeError>;
+
+/// Functions of a single backend
+pub(crate) struct Functions {
+    pub backend: Backend,
+    pub decode_raw: DecodeRaw,
+    pub validate_raw: ValidateRaw,
+    pub encode_raw: EncodeRaw,
+    /// Only needed by [`decode_skipping`](super::decode_skipping)
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub compact: Compact,
+}
+
+impl Functions {
+    #[cfg(feature = "alloc")]
+    pub fn decode(&self, input: &[u8], rule: CaseRule) -> Result<Vec<u8>, DecodeError> {
+        use DecodeError::*;
+
+        let c = input.len();
+        if c & 1 != 0 {
+            Err(OddLength)?
+        }
+
+        let mut v = Vec::with_capacity(c >> 1);
+        unsafe {
+            (self.decode_raw)(input.as_ptr(), c, v.as_mut_ptr(), rule)?;
+            v.set_len(c >> 1);
+        }
+
+        Ok(v)
+    }
+
+    pub fn decode_slice(
+        &self,
+        input: &[u8],
+        output: &mut [u8],
+        rule: CaseRule,
+    ) -> Result<usize, DecodeError> {
+        let n = decoded_len(input, output)?;
+        unsafe { (self.decode_raw)(input.as_ptr(), input.len(), output.as_mut_ptr(), rule)? };
+
+        Ok(n)
+    }
+
+    pub fn validate(&self, input: &[u8], rule: CaseRule) -> Result<(), DecodeError> {
+        use DecodeError::*;
+
+        if input.len() & 1 != 0 {
+            Err(OddLength)?
+        }
+
+        unsafe { (self.validate_raw)(input.as_ptr(), input.len(), rule) }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn encode_into(&self, output: &mut String, input: &[u8], case: Case) {
+        let n = input.len() << 1;
+        output.reserve(n);
+
+        // Only ASCII chars are written in the spare capacity
+        unsafe {
+            let v = output.as_mut_vec();
+            let len = v.len();
+            let dst = v.as_mut_ptr().add(len);
+            (self.encode_raw)(input.as_ptr(), input.len(), dst, case);
+            v.set_len(len + n);
+        }
+    }
+
+    pub fn encode_slice(
+        &self,
+        input: &[u8],
+        output: &mut [u8],
+        case: Case,
+    ) -> Result<usize, EncodeError> {
+        let n = encoded_len(input, output)?;
+        unsafe { (self.encode_raw)(input.as_ptr(), input.len(), output.as_mut_ptr(), case) };
+
+        Ok(n)
+    }
+}
+
+static FALLBACK: Functions = Functions {
+    backend: Backend::Fallback,
+    decode_raw: fallback::decode_raw,
+    validate_raw: fallback::validate_raw,
+    encode_raw: fallback::encode_raw,
+    compact: fallback::compact,
+};
+
+static SWAR: Functions = Functions {
+    backend: Backend::Swar,
+    decode_raw: swar::decode_raw,
+    validate_raw: swar::validate_raw,
+    encode_raw: swar::encode_raw,
+    compact: fallback::compact,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static SSE2: Functions = Functions {
+    backend: Backend::Ssse3,
+    decode_raw: sse2::decode_raw,
+    validate_raw: sse2::validate_raw,
+    encode_raw: sse2::encode_raw,
+    compact: sse2::compact,
+};
+
+// AVX2 can't shuffle bytes across its 128 bits lanes, so compacting
+// would do the same work of SSSE3
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static AVX2: Functions = Functions {
+    backend: Backend::Avx2,
+    decode_raw: avx2::decode_raw,
+    validate_raw: avx2::validate_raw,
+    encode_raw: avx2::encode_raw,
+    compact: sse2::compact,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static AVX512: Functions = Functions {
+    backend: Backend::Avx512,
+    decode_raw: avx512::decode_raw,
+    validate_raw: avx512::validate_raw,
+    encode_raw: avx512::encode_raw,
+    compact: sse2::compact,
+};
+
+/// Table of the selected backend, null until the first call
+static SELECTED: Selected<Functions> = Selected::new(Backend::functions);
+
+/// Functions of the [current](Backend::current) backend, the detection is
+/// only done once
+#[inline(always)]
+pub(crate) fn functions() -> &'static Functions {
+    // Picked at compile time, neither the CPU nor the environment are checked
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if crate::is_sse_ubiquitous!() {
+        return &SSE2;
+    }
+
+    SELECTED.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::BACKENDS;
+    use core::ptr;
+
+    #[test]
+    fn cached() {
+        let mut v = [0; 4];
+        unsafe { (functions().encode_raw)(b"\xa1\xf7".as_ptr(), 2, v.as_mut_ptr(), Case::Lower) };
+        assert_eq!(&v, b"a1f7");
+
+        assert!(ptr::eq(functions(), Backend::current().functions()));
+        assert_eq!(functions().backend, Backend::current());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn forced() {
+        let input: Vec<u8> = (0..100).map(|i| (i * 37) as u8).collect();
+        let expected = crate::hex::encode(&input);
+
+        for backend in BACKENDS.iter() {
+            let hex = match with_backend(*backend) {
+                Ok(hex) => hex,
+                Err(e) => {
+                    assert!(!backend.is_supported());
+                    assert_eq!(e, UnsupportedBackend(*backend));
+                    continue;
+                }
+            };
+
+            assert_eq!(hex.backend(), *backend);
+            assert_eq!(hex.encode(&input), expected);
+            assert_eq!(hex.decode(&expected).unwrap(), input);
+            let r = hex.decode("a1g7");
+            assert_eq!(r, Err(DecodeError::InvalidCharAt(2)), "{:?}", backend);
+
+            match hex.base64(base64::STANDARD) {
+                Ok(config) => {
+                    let expected = base64::encode(&input, base64::STANDARD);
+                    assert_eq!(base64::encode(&input, config), expected);
+                }
+                Err(e) => assert_eq!(e, UnsupportedBackend(*backend)),
+            }
+        }
+    }
+
+    #[test]
+    fn validating() {
+        use DecodeError::*;
+
+        let chars = b"0123456789abcdefABCDEF";
+        let step = if cfg!(miri) { 13 } else { 1 };
+
+        for backend in BACKENDS.iter() {
+            let hex = match with_backend(*backend) {
+                Ok(hex) => hex,
+                Err(_) => continue,
+            };
+
+            for len in (0..150).step_by(step) {
+                let input: Vec<u8> = (0..len).map(|i| chars[(i * 7) % chars.len()]).collect();
+                let mut output = vec![0; len >> 1];
+                if len & 1 != 0 {
+                    assert_eq!(hex.validate(&input), Err(OddLength));
+                    continue;
+                }
+                assert_eq!(hex.validate(&input), Ok(()), "{:?} {}", backend, len);
+
+                for i in (0..len).step_by(step) {
+                    let invalid = [
+                        b'g', b'p', b'q', b'v', b'w', b'/', b':', b'@', b'G', b'Q', b'V', b'`',
+                        0x80, 0xff, 0,
+                    ];
+                    for c in invalid.iter() {
+                        // A second invalid char at the end must not be reported
+                        let mut v = input.clone();
+                        v[i] = *c;
+                        v[len - 1] = b'x';
+
+                        let r = hex.validate(&v);
+                        assert_eq!(r, Err(InvalidCharAt(i)), "{:?} {} {}", backend, len, i);
+                        let d = hex.functions.decode_slice(&v, &mut output, CaseRule::Any);
+                        assert_eq!(r, d.map(|_| ()));
+                    }
+                }
+            }
+        }
+    }
+}
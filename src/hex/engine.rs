@@ -0,0 +1,226 @@
+//! Hex [`Engine`] configured through builder methods, the free functions
+//! of [`hex`](super) use its default configuration
+
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use super::dispatch::{self, Functions};
+use super::{
+    decode_in_place, Backend, Case, CaseRule, DecodeError, EncodeError, UnsupportedBackend,
+};
+use crate::Engine;
+
+/// Hex [`Engine`], encodes lowercase letters and decodes any case with the
+/// [current](Backend::current) backend unless configured otherwise
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use bintext::hex::{Case, CaseRule, DecodeError, Hex};
+/// use bintext::Engine;
+///
+/// let hex = Hex::new().case(Case::Upper).rule(CaseRule::Upper);
+/// assert_eq!(hex.encode(b"\xa1\xf7"), "A1F7");
+/// assert_eq!(hex.decode("a1F7"), Err(DecodeError::InvalidCharAt(0)));
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct Hex {
+    pub(super) case: Case,
+    pub(super) rule: CaseRule,
+    /// The selected backend is used when `None`
+    functions: Option<&'static Functions>,
+}
+
+impl Hex {
+    pub const fn new() -> Self {
+        Hex {
+            case: Case::Lower,
+            rule: CaseRule::Any,
+            functions: None,
+        }
+    }
+
+    /// Case of the encoded letters
+    pub const fn case(self, case: Case) -> Self {
+        Hex { case, ..self }
+    }
+
+    /// Letter case accepted while decoding
+    pub const fn rule(self, rule: CaseRule) -> Self {
+        Hex { rule, ..self }
+    }
+
+    /// Uses `backend` instead of the [current](Backend::current) one
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// use bintext::hex::{Backend, Hex};
+    /// use bintext::Engine;
+    ///
+    /// let hex = Hex::new().backend(Backend::Swar).unwrap();
+    /// assert_eq!(hex.encode(b"\xa1\xf7"), "a1f7");
+    /// # }
+    /// ```
+    pub fn backend(self, backend: Backend) -> Result<Self, UnsupportedBackend> {
+        if !backend.is_supported() {
+            Err(UnsupportedBackend(backend))?
+        }
+
+        Ok(Hex {
+            functions: Some(backend.functions()),
+            ..self
+        })
+    }
+
+    #[inline(always)]
+    pub(super) fn functions(&self) -> &'static Functions {
+        match self.functions {
+            Some(functions) => functions,
+            None => dispatch::functions(),
+        }
+    }
+
+    /// Same as [`hex::validate`](super::validate) but accepting only the
+    /// letters allowed by the rule
+    pub fn validate(&self, input: &[u8]) -> Result<(), DecodeError> {
+        self.functions().validate(input, self.rule)
+    }
+
+    /// Same as [`Engine::decode_aligned`] but accepts any padding, see
+    /// [`hex::decode_aligned_tight`](super::decode_aligned_tight)
+    ///
+    /// # Safety
+    ///
+    /// The contents of `input` are overwritten with the decoded bytes, so it
+    /// must not be used as a `str` afterwards.
+    pub unsafe fn decode_aligned_tight<'a>(
+        &self,
+        input: &'a mut str,
+        offset: usize,
+        align: usize,
+    ) -> Result<&'a mut [u8], DecodeError> {
+        decode_in_place(self, input.as_bytes_mut(), offset, align, true)
+    }
+}
+
+impl Default for Hex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hex")
+            .field("case", &self.case)
+            .field("rule", &self.rule)
+            .field(
+                "backend",
+                &self.functions.map(|functions| functions.backend),
+            )
+            .finish()
+    }
+}
+
+impl Engine for Hex {
+    type EncodeError = EncodeError;
+    type DecodeError = DecodeError;
+
+    #[inline]
+    fn encoded_len(&self, len: usize) -> usize {
+        len << 1
+    }
+
+    #[inline]
+    fn decoded_len_estimate(&self, len: usize) -> usize {
+        len >> 1
+    }
+
+    #[cfg(feature = "alloc")]
+    fn encode_into(&self, output: &mut String, input: &[u8]) {
+        self.functions().encode_into(output, input, self.case)
+    }
+
+    fn encode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        self.functions().encode_slice(input, output, self.case)
+    }
+
+    fn decode_slice(&self, input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        self.functions().decode_slice(input, output, self.rule)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn decode<T: AsRef<[u8]>>(&self, input: T) -> Result<Vec<u8>, DecodeError> {
+        self.functions().decode(input.as_ref(), self.rule)
+    }
+
+    unsafe fn decode_aligned<'a>(
+        &self,
+        input: &'a mut str,
+        offset: usize,
+        align: usize,
+    ) -> Result<&'a mut [u8], DecodeError> {
+        decode_in_place(self, input.as_bytes_mut(), offset, align, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn configured() {
+        let input: Vec<u8> = (0..100).map(|i| (i * 37) as u8).collect();
+        let lower = crate::hex::encode(&input);
+        let upper = crate::hex::encode_upper(&input);
+
+        let hex = Hex::new();
+        assert_eq!(hex.encode(&input), lower);
+        assert_eq!(hex.decode(&upper).unwrap(), input);
+        assert_eq!(hex.encoded_len(input.len()), lower.len());
+        assert_eq!(hex.decoded_len_estimate(lower.len()), input.len());
+
+        let hex = hex.case(Case::Upper).rule(CaseRule::Lower);
+        assert_eq!(hex.encode(&input), upper);
+        assert_eq!(hex.decode(&lower).unwrap(), input);
+        assert!(hex.decode(&upper).is_err());
+        assert!(hex.validate(upper.as_bytes()).is_err());
+
+        let mut v = vec![0; input.len()];
+        assert_eq!(hex.decode_slice(lower.as_bytes(), &mut v), Ok(input.len()));
+        assert_eq!(v, input);
+
+        let mut v = vec![0; upper.len()];
+        assert_eq!(hex.encode_slice(&input, &mut v), Ok(upper.len()));
+        assert_eq!(v, upper.as_bytes());
+        assert_eq!(
+            hex.encode_slice(&input, &mut v[1..]),
+            Err(EncodeError::OutputTooSmall {
+                needed: upper.len(),
+                got: upper.len() - 1
+            })
+        );
+
+        let hex = hex.backend(Backend::Fallback).unwrap();
+        assert_eq!(hex.encode(&input), upper);
+        assert_eq!(
+            format!("{:?}", hex),
+            "Hex { case: Upper, rule: Lower, backend: Some(Fallback) }"
+        );
+    }
+
+    #[test]
+    fn aligned() {
+        let mut v = "----A1f7".to_string();
+        let hex = Hex::new().rule(CaseRule::Lower);
+        let r = unsafe { hex.decode_aligned(&mut v, 4, 4) };
+        assert_eq!(r, Err(DecodeError::InvalidCharAt(0)));
+
+        let mut v = "----a1f7".to_string();
+        let r = unsafe { hex.decode_aligned(&mut v, 4, 4).unwrap() };
+        assert_eq!(r, b"\xa1\xf7");
+    }
+}
@@ -1,70 +1,96 @@
-#![allow(dead_code)]
+use super::{Case, CaseRule, DecodeError, SkipSet, HEX_NIBBLE_DECODE};
 
-use super::{alloc, DecodeError, HEX_ENCODE, HEX_NIBBLE_DECODE};
-
-#[inline(always)]
-pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
-    use DecodeError::*;
-
-    let l = input.len();
-    if l & 1 != 0 {
-        Err(OddLength)?
-    }
-
-    let mut v = alloc(l >> 1);
-    decode_noalloc(input.as_bytes(), &mut v[..])?;
-
-    Ok(v)
-}
-
-pub fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+/// Decodes the `len` chars at `src` into `dst`
+///
+/// # Safety
+///
+/// `len` must be even, `src` must be valid for `len` reads and `dst` for
+/// `len / 2` writes, they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    len: usize,
+    dst: *mut u8,
+    rule: CaseRule,
+) -> Result<(), DecodeError> {
     use DecodeError::*;
 
-    let len = input.len();
+    let nibbles = rule.nibbles();
 
     let mut i = 0;
     let mut j = 0;
 
     while i < len {
-        unsafe {
-            let msn = *HEX_NIBBLE_DECODE.get_unchecked(*input.get_unchecked(i) as usize);
-            if msn > 0xf {
-                Err(InvalidCharAt(i))?
-            }
-
-            let lsn = *HEX_NIBBLE_DECODE.get_unchecked(*input.get_unchecked(i + 1) as usize);
-            if lsn > 0xf {
-                Err(InvalidCharAt(i + 1))?
-            }
-
-            *output.get_unchecked_mut(j) = (msn << 4) | lsn;
-            i += 2;
-            j += 1;
+        let msn = *nibbles.get_unchecked(*src.add(i) as usize);
+        if msn > 0xf {
+            Err(InvalidCharAt(i))?
+        }
+
+        let lsn = *nibbles.get_unchecked(*src.add(i + 1) as usize);
+        if lsn > 0xf {
+            Err(InvalidCharAt(i + 1))?
         }
+
+        *dst.add(j) = (msn << 4) | lsn;
+        i += 2;
+        j += 1;
     }
 
     Ok(())
 }
 
-#[inline(always)]
-pub fn encode(input: &[u8]) -> String {
-    let mut i = 0usize;
-    let mut v = alloc(input.len() << 1);
-    unsafe {
-        for b in input {
-            let j = (*b as usize) << 1;
-            *v.get_unchecked_mut(i) = *HEX_ENCODE.get_unchecked(j);
-            *v.get_unchecked_mut(i | 1) = *HEX_ENCODE.get_unchecked(j | 1);
-            i += 2;
+/// Checks if the `len` chars at `src` are valid hex, the length isn't checked
+///
+/// # Safety
+///
+/// `src` must be valid for `len` reads
+pub unsafe fn validate_raw(src: *const u8, len: usize, rule: CaseRule) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let nibbles = rule.nibbles();
+    for i in 0..len {
+        if *nibbles.get_unchecked(*src.add(i) as usize) > 0xf {
+            Err(InvalidCharAt(i))?
         }
-        String::from_utf8_unchecked(v)
+    }
+
+    Ok(())
+}
+
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// `src` must be valid for `len` reads and `dst` for `len * 2` writes,
+/// without overlapping
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, case: Case) {
+    let table = case.table();
+    for i in 0..len {
+        let j = (*src.add(i) as usize) << 1;
+        *dst.add(i << 1) = *table.get_unchecked(j);
+        *dst.add((i << 1) | 1) = *table.get_unchecked(j | 1);
     }
 }
 
-#[inline(always)]
-#[allow(dead_code)]
-pub fn meet_requirements() -> bool {
-    return true;
+/// Moves every char of `input` not in `skip` to the start of `output`,
+/// returning how many were kept
+pub fn compact(input: &[u8], skip: &SkipSet, output: &mut [u8]) -> Result<usize, DecodeError> {
+    use DecodeError::*;
+
+    let mut j = 0;
+    for (i, c) in input.iter().enumerate() {
+        if skip.contains(*c) {
+            continue;
+        }
+
+        if HEX_NIBBLE_DECODE[*c as usize] > 0xf {
+            Err(InvalidCharAt(i))?
+        }
+
+        output[j] = *c;
+        j += 1;
+    }
+
+    Ok(j)
 }
 
-crate::tests_hex!(super::encode, super::decode, super::meet_requirements);
+crate::tests_hex!(crate::hex::Backend::Fallback);
@@ -0,0 +1,242 @@
+//! Self describing alignment header, written at the start of the padding
+//! as `~<align>.<len>~` where both numbers are in hex, `len` is the number
+//! of bytes of the payload that ends the string.
+
+use super::{decode_in_place, DecodeError, Hex, HEX_NIBBLE_DECODE};
+
+#[cfg(feature = "alloc")]
+use super::{aligned_padding, encode_into, FILL};
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+
+/// Marks the start and the end of the header
+const MARKER: u8 = b'~';
+/// Separates the alignment from the payload length
+const SEPARATOR: u8 = b'.';
+
+#[inline(always)]
+fn is_hex(c: u8) -> bool {
+    HEX_NIBBLE_DECODE[c as usize] <= 0xf
+}
+
+/// Parses an hex number that ends with `end`, `i` is left after `end`
+fn parse_number(bytes: &[u8], i: &mut usize, end: u8) -> Result<usize, DecodeError> {
+    use DecodeError::*;
+
+    let start = *i;
+    let mut n = 0usize;
+    loop {
+        let c = *bytes.get(*i).ok_or(MalformedHeaderAt(*i))?;
+        if c == end && *i > start {
+            *i += 1;
+            return Ok(n);
+        }
+
+        if !is_hex(c) {
+            Err(MalformedHeaderAt(*i))?
+        }
+        let nibble = HEX_NIBBLE_DECODE[c as usize];
+
+        n = n
+            .checked_mul(16)
+            .map(|n| n | nibble as usize)
+            .ok_or(MalformedHeaderAt(*i))?;
+        *i += 1;
+    }
+}
+
+/// Parses the header returning the alignment, the payload length and the header length
+fn parse_header(bytes: &[u8]) -> Result<(usize, usize, usize), DecodeError> {
+    use DecodeError::*;
+
+    if bytes.first() != Some(&MARKER) {
+        Err(MissingHeader)?
+    }
+
+    let mut i = 1;
+    let align = parse_number(bytes, &mut i, SEPARATOR)?;
+    if !align.is_power_of_two() {
+        Err(MalformedHeaderAt(1))?
+    }
+
+    let len = parse_number(bytes, &mut i, MARKER)?;
+    Ok((align, len, i))
+}
+
+/// Decodes a hex str written by [`encode_aligned_auto`], the offset and
+/// alignment are read from its header.
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// let mut hex = bintext::hex::encode_aligned_auto(b"\xa1\xf7\xd5\xe8", 8);
+/// assert_eq!(hex, "~8.4~---a1f7d5e8");
+///
+/// unsafe {
+///     let slice = bintext::hex::decode_aligned_auto(&mut hex).unwrap();
+///     assert_eq!(slice, b"\xa1\xf7\xd5\xe8");
+///     assert_eq!(slice.as_ptr().align_offset(8), 0);
+/// }
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// The contents of `input` are overwritten with the decoded bytes, so it must
+/// not be used as a `str` afterwards.
+pub unsafe fn decode_aligned_auto(input: &mut str) -> Result<&mut [u8], DecodeError> {
+    use DecodeError::*;
+
+    let bytes = input.as_bytes_mut();
+    let (align, len, header) = parse_header(bytes)?;
+
+    // Payload ends the string, the remaining chars are the padding
+    let available = (bytes.len() - header) >> 1;
+    if len > available {
+        Err(HeaderLengthMismatch {
+            expected: len,
+            got: available,
+        })?
+    }
+
+    // Fill chars are never hex, so hex chars right before the payload mean
+    // it's longer than the header says
+    let offset = bytes.len() - (len << 1);
+    let extra = bytes[header..offset]
+        .iter()
+        .rev()
+        .take_while(|&&c| is_hex(c))
+        .count();
+    if extra > 0 {
+        Err(HeaderLengthMismatch {
+            expected: len,
+            got: len + (extra >> 1),
+        })?
+    }
+
+    decode_in_place(&Hex::new(), bytes, offset, align, false)
+}
+
+/// Encodes `input` with a padding of at least `align` fill chars in front of it,
+/// starting with a header describing the alignment and the length of `input`,
+/// see [`decode_aligned_auto`].
+///
+/// # Panics
+///
+/// When `align` isn't a power of two
+#[cfg(feature = "alloc")]
+pub fn encode_aligned_auto(input: &[u8], align: usize) -> String {
+    let mut output = String::new();
+    encode_aligned_auto_into(&mut output, input, align, FILL);
+    output
+}
+
+/// Appends `input` encoded with the header and padding required by
+/// [`decode_aligned_auto`], the padding is computed for the current length
+/// of `output` just like [`encode_aligned_into`](super::encode_aligned_into).
+///
+/// Returns the padding length, header included.
+///
+/// # Panics
+///
+/// When `align` isn't a power of two or `fill` isn't an ASCII char other than
+/// an hex digit
+#[cfg(feature = "alloc")]
+pub fn encode_aligned_auto_into(
+    output: &mut String,
+    input: &[u8],
+    align: usize,
+    fill: u8,
+) -> usize {
+    assert!(align.is_power_of_two(), "align must be a power of two");
+    assert!(fill.is_ascii(), "fill must be an ASCII char");
+    assert!(!is_hex(fill), "fill must not be an hex digit");
+
+    let header = format!("~{:x}.{:x}~", align, input.len());
+    let padding = aligned_padding(output.len(), align, header.len());
+    output.reserve(padding + (input.len() << 1));
+    output.push_str(&header);
+    output.extend(core::iter::repeat_n(fill as char, padding - header.len()));
+    encode_into(output, input);
+    padding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encoding() {
+        assert_eq!(encode_aligned_auto(b"", 1), "~1.0~");
+        assert_eq!(encode_aligned_auto(b"\x02\x03", 4), "~4.2~---0203");
+        assert_eq!(encode_aligned_auto(b"\x02\x03", 16), "~10.2~----------0203");
+
+        let mut v = "id".to_string();
+        assert_eq!(encode_aligned_auto_into(&mut v, b"\x02", 4, b' '), 6);
+        assert_eq!(v, "id~4.1~ 02");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn decoding() {
+        let input: Vec<u8> = (0..200).map(|i| (i * 37) as u8).collect();
+        let aligns: &[usize] = if cfg!(miri) {
+            &[1, 8, 64]
+        } else {
+            &[1, 2, 4, 8, 16, 32, 64, 4096]
+        };
+        for align in aligns.iter() {
+            for len in [0, 1, 3, 16, 33, 200].iter() {
+                for position in 0..3 {
+                    let mut v = "#".repeat(position);
+                    encode_aligned_auto_into(&mut v, &input[..*len], *align, FILL);
+                    let v = unsafe { decode_aligned_auto(&mut v[position..]).unwrap() };
+                    assert_eq!(v, &input[..*len]);
+                    assert_eq!(v.as_ptr().align_offset(*align), 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        use DecodeError::*;
+
+        const SAMPLES: [(&str, DecodeError); 10] = [
+            ("", MissingHeader),
+            ("----0203", MissingHeader),
+            ("~", MalformedHeaderAt(1)),
+            ("~.2~---0203", MalformedHeaderAt(1)),
+            ("~4.~---0203", MalformedHeaderAt(3)),
+            ("~4:2~---0203", MalformedHeaderAt(2)),
+            ("~3.2~---0203", MalformedHeaderAt(1)),
+            ("~4.2", MalformedHeaderAt(4)),
+            (
+                "~4.4~---0203",
+                HeaderLengthMismatch {
+                    expected: 4,
+                    got: 3,
+                },
+            ),
+            (
+                "~4.1~---0203",
+                HeaderLengthMismatch {
+                    expected: 1,
+                    got: 2,
+                },
+            ),
+        ];
+
+        for (input, expected) in SAMPLES.iter() {
+            let mut v = input.to_string();
+            let r = unsafe { decode_aligned_auto(&mut v) };
+            assert_eq!(r.unwrap_err(), *expected, "{:?}", input);
+        }
+
+        // Length overflow
+        let mut v = format!("~4.{}~---0203", "f".repeat(40));
+        let r = unsafe { decode_aligned_auto(&mut v) };
+        let n = core::mem::size_of::<usize>() << 1;
+        assert_eq!(r.unwrap_err(), MalformedHeaderAt(3 + n));
+    }
+}
@@ -0,0 +1,177 @@
+//! Hex literals decoded at compile time, for keys and magic constants
+//! embedded in the source
+
+use super::HEX_NIBBLE_DECODE;
+
+/// Decodes `input` into an array, usable in const contexts where any error
+/// fails the build, see [`hex!`](crate::hex!).
+///
+/// ```rust
+/// const MAGIC: [u8; 4] = bintext::hex::decode_array("a1F7d5e8");
+/// assert_eq!(MAGIC, [0xa1, 0xf7, 0xd5, 0xe8]);
+/// ```
+///
+/// # Panics
+///
+/// When `input` isn't made of `N * 2` hex chars
+pub const fn decode_array<const N: usize>(input: &str) -> [u8; N] {
+    let bytes = input.as_bytes();
+    if bytes.len() & 1 != 0 {
+        panic!("odd number of hex chars");
+    }
+    if bytes.len() >> 1 != N {
+        panic!("hex length doesn't match the array length");
+    }
+
+    let mut output = [0; N];
+    let mut i = 0;
+    while i < N {
+        let hi = HEX_NIBBLE_DECODE[bytes[i << 1] as usize];
+        let lo = HEX_NIBBLE_DECODE[bytes[(i << 1) + 1] as usize];
+        if (hi | lo) > 0xf {
+            panic!("invalid hex char");
+        }
+
+        output[i] = (hi << 4) | lo;
+        i += 1;
+    }
+
+    output
+}
+
+/// Decodes an hex literal at compile time into a `[u8; N]` sized by the
+/// literal itself
+///
+/// ```rust
+/// const KEY: [u8; 4] = bintext::hex!("a1f7d5e8");
+/// assert_eq!(KEY, [0xa1, 0xf7, 0xd5, 0xe8]);
+///
+/// let magic = bintext::hex!(concat!("a1f7", "D5E8"));
+/// assert_eq!(magic, KEY);
+/// ```
+///
+/// Invalid chars or an odd length don't compile
+///
+/// ```compile_fail
+/// let key = bintext::hex!("a1f7d5e");
+/// ```
+///
+/// ```compile_fail
+/// let key = bintext::hex!("a1f7d5eg");
+/// ```
+#[macro_export]
+macro_rules! hex {
+    ($hex:expr) => {{
+        const INPUT: &str = $hex;
+        const OUTPUT: [u8; INPUT.len() >> 1] = $crate::hex::decode_array(INPUT);
+        OUTPUT
+    }};
+}
+
+/// Declares a static decoded at compile time from an hex literal, its type is
+/// a struct of the same name with `#[repr(align(N))]` holding the decoded
+/// `bytes`
+///
+/// ```rust
+/// bintext::aligned_hex! {
+///     /// Key used by the tests
+///     pub static KEY: align(16) = "a1f7d5e8d14f0f76";
+/// }
+///
+/// assert_eq!(KEY.bytes, [0xa1, 0xf7, 0xd5, 0xe8, 0xd1, 0x4f, 0x0f, 0x76]);
+/// assert_eq!(core::mem::align_of_val(&KEY), 16);
+/// assert_eq!(KEY.bytes.as_ptr() as usize % 16, 0);
+/// ```
+///
+/// Same as [`hex!`](crate::hex!) invalid literals don't compile
+///
+/// ```compile_fail
+/// bintext::aligned_hex! {
+///     static KEY: align(16) = "a1f7d5e8d14f0f7";
+/// }
+/// ```
+#[macro_export]
+macro_rules! aligned_hex {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: align($align:literal) = $hex:expr;) => {
+        $(#[$attr])*
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[repr(align($align))]
+        $vis struct $name {
+            pub bytes: [u8; $hex.len() >> 1],
+        }
+
+        $(#[$attr])*
+        $vis static $name: $name = $name {
+            bytes: $crate::hex::decode_array($hex),
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::hex::{encode, encode_upper};
+
+    crate::aligned_hex! {
+        static SHORT: align(8) = "a1f7";
+    }
+
+    crate::aligned_hex! {
+        static PAGE: align(4096) = concat!("00112233", "44556677", "8899aabb", "ccddeeff");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn decoding() {
+        const ALL: [u8; 256] = decode_array(concat!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f",
+            "404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f",
+            "606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f",
+            "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+            "A0A1A2A3A4A5A6A7A8A9AAABACADAEAFB0B1B2B3B4B5B6B7B8B9BABBBCBDBEBF",
+            "C0C1C2C3C4C5C6C7C8C9CACBCCCDCECFD0D1D2D3D4D5D6D7D8D9DADBDCDDDEDF",
+            "E0E1E2E3E4E5E6E7E8E9EAEBECEDEEEFF0F1F2F3F4F5F6F7F8F9FAFBFCFDFEFF",
+        ));
+        let expected: Vec<u8> = (0..=255).collect();
+        assert_eq!(&ALL[..], &expected[..]);
+
+        let bytes: Vec<u8> = (0..64).map(|i| (i * 37) as u8).collect();
+        let lower = encode(&bytes);
+        let upper = encode_upper(&bytes);
+        assert_eq!(decode_array::<64>(&lower), &bytes[..]);
+        assert_eq!(decode_array::<64>(&upper), &bytes[..]);
+
+        assert_eq!(crate::hex!(""), [0u8; 0]);
+        assert_eq!(crate::hex!("a1F7"), [0xa1, 0xf7]);
+    }
+
+    #[test]
+    fn aligned() {
+        assert_eq!(SHORT.bytes, [0xa1, 0xf7]);
+        assert_eq!(SHORT.bytes.as_ptr() as usize % 8, 0);
+
+        let expected: Vec<u8> = (0..16).map(|i| i * 0x11).collect();
+        assert_eq!(&PAGE.bytes[..], &expected[..]);
+        assert_eq!(PAGE.bytes.as_ptr() as usize % 4096, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "odd number of hex chars")]
+    fn odd_length() {
+        decode_array::<2>("a1f");
+    }
+
+    #[test]
+    #[should_panic(expected = "hex length doesn't match the array length")]
+    fn length_mismatch() {
+        decode_array::<1>("a1f7");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid hex char")]
+    fn invalid_char() {
+        decode_array::<2>("a1\u{e9}");
+    }
+}
@@ -0,0 +1,246 @@
+//! Hex decoding that skips whitespace and separators, like the ones found
+//! in `xxd` dumps or formatted config files
+
+#[cfg(feature = "alloc")]
+use super::{dispatch, ChunkedDecoder, DecodeError};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Set of ASCII chars stored as a bitset, indexed first by the least
+/// significant nibble then by the most significant one, this layout
+/// allows SIMD lookups with a single shuffle
+const fn bitset(bytes: &[u8]) -> [u8; 16] {
+    let mut lut = [0; 16];
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        assert!(c.is_ascii(), "only ASCII chars can be in the set");
+        lut[(c & 0xf) as usize] |= 1 << (c >> 4);
+        i += 1;
+    }
+    lut
+}
+
+/// Every valid hex char
+pub(crate) const HEX_BITSET: [u8; 16] = bitset(b"0123456789abcdefABCDEF");
+
+/// Shuffle indexes that moves the lanes set in the 8 bits mask to the start
+pub(crate) const COMPACT: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        let mut entry = 0u64;
+        let mut j = 0;
+        let mut i = 0;
+        while i < 8 {
+            if mask & (1 << i) != 0 {
+                entry |= (i as u64) << (j << 3);
+                j += 1;
+            }
+            i += 1;
+        }
+        table[mask] = entry;
+        mask += 1;
+    }
+    table
+};
+
+/// Set of ASCII chars skipped by [`decode_skipping`]
+///
+/// ```rust
+/// use bintext::hex::SkipSet;
+///
+/// let skip = SkipSet::new(b" |");
+/// assert!(skip.contains(b'|'));
+/// assert!(!skip.contains(b'\n'));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SkipSet {
+    pub(crate) lut: [u8; 16],
+}
+
+impl SkipSet {
+    /// Spaces, tabs and line breaks
+    pub const WHITESPACE: Self = Self::new(b" \t\n\r");
+
+    /// Whitespace plus the usual byte separators `:`, `-`, `,` and `;`
+    pub const SEPARATORS: Self = Self::new(b" \t\n\r:-,;");
+
+    /// Creates a set of the given chars
+    ///
+    /// # Panics
+    ///
+    /// When any of the chars isn't ASCII or is a valid hex char
+    pub const fn new(bytes: &[u8]) -> Self {
+        let lut = bitset(bytes);
+        let mut i = 0;
+        while i < 16 {
+            assert!(lut[i] & HEX_BITSET[i] == 0, "hex chars can't be skipped");
+            i += 1;
+        }
+        Self { lut }
+    }
+
+    pub const fn contains(&self, c: u8) -> bool {
+        c.is_ascii() && self.lut[(c & 0xf) as usize] & (1 << (c >> 4)) != 0
+    }
+}
+
+impl core::fmt::Debug for SkipSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set()
+            .entries((0..128u8).filter(|c| self.contains(*c)).map(char::from))
+            .finish()
+    }
+}
+
+/// Chars compacted at once, small enough to live in the stack
+#[cfg(feature = "alloc")]
+const BLOCK: usize = 512;
+
+/// Decodes an hex string ignoring every char of `skip`, error positions
+/// are relative to the original `input`.
+///
+/// ```rust
+/// use bintext::hex::{decode_skipping, SkipSet};
+///
+/// let v = decode_skipping("de:ad:be:ef\nca fe", &SkipSet::SEPARATORS).unwrap();
+/// assert_eq!(v, b"\xde\xad\xbe\xef\xca\xfe");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_skipping(input: &str, skip: &SkipSet) -> Result<Vec<u8>, DecodeError> {
+    let input = input.as_bytes();
+    let mut v = vec![0; input.len() >> 1];
+    let mut buffer = [0; BLOCK];
+    let mut decoder = ChunkedDecoder::new();
+    let mut j = 0;
+
+    for (k, block) in input.chunks(BLOCK).enumerate() {
+        let n = compact(block, skip, &mut buffer).map_err(|e| e.shifted(k * BLOCK))?;
+
+        // Only hex chars are left and `v` can hold half of the input
+        j += decoder.feed(&buffer[..n], &mut v[j..])?;
+    }
+
+    decoder.finish()?;
+    v.truncate(j);
+
+    Ok(v)
+}
+
+/// Moves every char of `input` not in `skip` to the start of `output`,
+/// returning how many were kept. `output` must be as long as `input`.
+#[cfg(feature = "alloc")]
+fn compact(input: &[u8], skip: &SkipSet, output: &mut [u8]) -> Result<usize, DecodeError> {
+    unsafe { (dispatch::functions().compact)(input, skip, output) }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::hex::{encode, fallback};
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    use crate::hex::{sse2, Backend};
+
+    fn data() -> Vec<u8> {
+        let len = if cfg!(miri) { 400 } else { 1500 };
+        (0..len).map(|i| (i * 37) as u8).collect()
+    }
+
+    /// Formats the hex of `input` putting `separator` after every `step` chars
+    fn formatted(input: &[u8], step: usize, separator: &str) -> String {
+        let hex = encode(input);
+        let mut v = String::new();
+        for chunk in hex.as_bytes().chunks(step) {
+            v.push_str(core::str::from_utf8(chunk).unwrap());
+            v.push_str(separator);
+        }
+        v
+    }
+
+    #[test]
+    fn sets() {
+        for c in 0..=255u8 {
+            let whitespace = c == b' ' || c == b'\t' || c == b'\n' || c == b'\r';
+            assert_eq!(SkipSet::WHITESPACE.contains(c), whitespace);
+        }
+        assert_eq!(format!("{:?}", SkipSet::new(b"|:")), "{':', '|'}");
+    }
+
+    #[test]
+    fn decoding() {
+        let expected = data();
+
+        for (step, separator) in [
+            (2, " "),
+            (2, ":"),
+            (4, " "),
+            (3, "-"),
+            (32, "\n"),
+            (1, "\r\n"),
+            (7, " \t ;"),
+            (64, ""),
+        ]
+        .iter()
+        {
+            let input = formatted(&expected, *step, separator);
+            let v = decode_skipping(&input, &SkipSet::SEPARATORS).unwrap();
+            assert_eq!(v, expected, "{} {:?}", step, separator);
+        }
+
+        assert_eq!(decode_skipping("", &SkipSet::WHITESPACE).unwrap(), b"");
+        assert_eq!(decode_skipping(" \n ", &SkipSet::WHITESPACE).unwrap(), b"");
+    }
+
+    #[test]
+    fn decoding_errors() {
+        use DecodeError::*;
+
+        let input = formatted(&data(), 2, " ");
+        for i in (0..input.len()).step_by(if cfg!(miri) { 97 } else { 7 }) {
+            for c in ["g", ":", "\x7f", "\0"].iter() {
+                let mut v = input.clone();
+                v.replace_range(i..(i + 1), c);
+                let r = decode_skipping(&v, &SkipSet::WHITESPACE);
+                assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", v);
+            }
+
+            let mut v = input.clone();
+            v.replace_range(i..(i + 1), "é");
+            let r = decode_skipping(&v, &SkipSet::WHITESPACE);
+            assert_eq!(r.unwrap_err(), InvalidCharAt(i));
+        }
+
+        let r = decode_skipping("de ad b", &SkipSet::WHITESPACE);
+        assert_eq!(r.unwrap_err(), OddLength);
+    }
+
+    #[test]
+    fn compacting() {
+        let chars = b"0123456789abcdefABCDEF :-\n";
+        let skip = SkipSet::SEPARATORS;
+
+        for len in 0..100 {
+            let input: Vec<u8> = (0..len)
+                .map(|i| chars[(i * 7 + len * 3) % chars.len()])
+                .collect();
+            let expected: Vec<u8> = input
+                .iter()
+                .copied()
+                .filter(|c| !skip.contains(*c))
+                .collect();
+
+            let mut v = vec![0; len];
+            let n = fallback::compact(&input, &skip, &mut v).unwrap();
+            assert_eq!(&v[..n], &expected[..]);
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            if Backend::Ssse3.is_supported() {
+                let mut v = vec![0; len];
+                let n = unsafe { sse2::compact(&input, &skip, &mut v).unwrap() };
+                assert_eq!(&v[..n], &expected[..]);
+            }
+        }
+    }
+}
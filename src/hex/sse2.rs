@@ -1,77 +1,150 @@
-#![allow(dead_code)]
-
 #[cfg(target_arch = "x86")]
-use std::arch::x86::*;
+use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use core::arch::x86_64::*;
 
 use super::*;
-use std::ptr::copy_nonoverlapping;
-
-///////////////////////////////////////////////////////////////////////////////
-
-#[inline(always)]
-pub unsafe fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
-    use DecodeError::*;
-
-    // Input check
-    let c = input.len();
-    if c & 1 != 0 {
-        Err(OddLength)?
-    }
-
-    let mut v = super::alloc(c >> 1);
-    decode_noalloc(input.as_bytes(), v.as_mut_slice())?;
-
-    Ok(v)
-}
+use core::ptr::copy_nonoverlapping;
 
 ///////////////////////////////////////////////////////////////////////////////
 
-pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+/// Decodes the `len` chars at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `len` must
+/// be even, `src` must be valid for `len` reads and `dst` for `len / 2` writes,
+/// they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    len: usize,
+    dst: *mut u8,
+    rule: CaseRule,
+) -> Result<(), DecodeError> {
     use DecodeError::*;
 
     // Constants
-    let lutx3 = _mm_set_epi64x(HEX_DECODE_64LUT_X30_1, HEX_DECODE_64LUT_X30_0);
-    let lutx4and6 = _mm_set_epi64x(0, HEX_DECODE_64LUT_AZ);
-
-    let x30 = _mm_set1_epi8(0x30u8 as i8);
-    let x3f = _mm_set1_epi8(0x3fu8 as i8);
-    let x40 = _mm_set1_epi8(0x40u8 as i8);
-    let x50 = _mm_set1_epi8(0x50u8 as i8);
-    let x5f = _mm_set1_epi8(0x5fu8 as i8);
-    let x60 = _mm_set1_epi8(0x60u8 as i8);
+    let lookup = Lookup::new(rule);
 
     let m = _mm_set1_epi16(0x00FFu16 as i16);
     let idec = _mm_set_epi64x(-1, 0x0f_0d_0b_09_07_05_03_01u64 as i64);
     let tmpsll = _mm_set1_epi64x(12);
     let filled = _mm_set1_epi64x(-1);
 
-    // Input pointers
-    let mut p = input.as_ptr() as *const i8;
-    let p_end = p.add(input.len());
+    // Decodes the 16 chars at `p` into the 8 bytes at `b`, returns the mask
+    // of the invalid chars, nothing is written when there is any
+    let block = |p: *const u8, b: *mut u8| {
+        // TODO: how about _mm_lddqu_si128?
+        let slice = _mm_loadu_si128(p as *const __m128i);
+
+        let (dec, ok) = lookup.apply(slice);
+        if ok != 0xffff {
+            return !ok & 0xffff;
+        }
+
+        let dec = {
+            // Pick even bytes containing most significant nibbles
+            let temp = _mm_andnot_si128(dec, m);
+            // Peform a 12 bit shift
+            let temp = _mm_sll_epi64(temp, tmpsll);
+            _mm_andnot_si128(temp, dec)
+        };
+
+        // Takes only odd bytes
+        // Final result, must be fliped
+        let dec = _mm_andnot_si128(_mm_shuffle_epi8(dec, idec), filled);
 
-    let mut b = output.as_mut_ptr();
+        // Saves the final result
+        copy_nonoverlapping(&dec as *const _ as *const u8, b, 8);
+        0
+    };
 
     // Main loop loop
-    while p.offset(15) < p_end {
-        // TODO: how about _mm_lddqu_si128?
-        let slice = _mm_loadu_si128(p as *const __m128i);
+    let mut i = 0;
+    while len - i >= 16 {
+        let bad = block(src.add(i), dst.add(i >> 1));
+        if bad != 0 {
+            // First set bit is the first invalid char of the slice
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
+        }
+
+        i += 16;
+    }
+
+    if i == len {
+        return Ok(());
+    }
+
+    // The remaining chars can't be read again by an overlapping load, since
+    // in place decoding may have already overwritten them, so they cascade
+    // to SWAR
+    swar::decode_raw(src.add(i), len - i, dst.add(i >> 1), rule).map_err(|e| e.shifted(i))
+}
+
+/// LUT range checks shared by decoding and validation
+struct Lookup {
+    lutx3: __m128i,
+    lutx4: __m128i,
+    lutx6: __m128i,
+    x2f: __m128i,
+    x30: __m128i,
+    x3f: __m128i,
+    x40: __m128i,
+    x50: __m128i,
+    x5f: __m128i,
+    x60: __m128i,
+    x70: __m128i,
+}
+
+impl Lookup {
+    #[inline(always)]
+    unsafe fn new(rule: CaseRule) -> Self {
+        let (az4, az6) = rule.lut();
+        Self {
+            lutx3: _mm_set_epi64x(HEX_DECODE_64LUT_X30_1, HEX_DECODE_64LUT_X30_0),
+            lutx4: _mm_set_epi64x(0, az4),
+            lutx6: _mm_set_epi64x(0, az6),
+            x2f: _mm_set1_epi8(0x2fu8 as i8),
+            x30: _mm_set1_epi8(0x30u8 as i8),
+            x3f: _mm_set1_epi8(0x3fu8 as i8),
+            x40: _mm_set1_epi8(0x40u8 as i8),
+            x50: _mm_set1_epi8(0x50u8 as i8),
+            x5f: _mm_set1_epi8(0x5fu8 as i8),
+            x60: _mm_set1_epi8(0x60u8 as i8),
+            x70: _mm_set1_epi8(0x70u8 as i8),
+        }
+    }
 
+    /// Returns the inverted nibbles of `slice` and the mask of its valid chars
+    #[inline(always)]
+    unsafe fn apply(&self, slice: __m128i) -> (__m128i, u32) {
         // Calculates LUT range masks
-        let mx6 = _mm_cmpgt_epi8(slice, x5f);
-        let mx4 = _mm_and_si128(_mm_cmpgt_epi8(slice, x3f), _mm_cmplt_epi8(slice, x50));
-        let mx3 = _mm_cmplt_epi8(slice, x40);
+        // Without the upper bounds the shuffles would wrap the letters
+        // past 'f' or 'F' to the valid ones
+        let mx6 = _mm_and_si128(
+            _mm_cmpgt_epi8(slice, self.x5f),
+            _mm_cmplt_epi8(slice, self.x70),
+        );
+        let mx4 = _mm_and_si128(
+            _mm_cmpgt_epi8(slice, self.x3f),
+            _mm_cmplt_epi8(slice, self.x50),
+        );
+        // Non ASCII chars are negative, without the lower bound some of them
+        // would index the digits
+        let mx3 = _mm_and_si128(
+            _mm_cmpgt_epi8(slice, self.x2f),
+            _mm_cmplt_epi8(slice, self.x40),
+        );
 
         // LUT indexes
-        let ix3 = _mm_sub_epi8(slice, x30);
-        let ix4 = _mm_sub_epi8(slice, x40);
-        let ix6 = _mm_sub_epi8(slice, x60);
+        let ix3 = _mm_sub_epi8(slice, self.x30);
+        let ix4 = _mm_sub_epi8(slice, self.x40);
+        let ix6 = _mm_sub_epi8(slice, self.x60);
 
         // LUT sample
-        let vx3 = _mm_shuffle_epi8(lutx3, ix3);
-        let vx4 = _mm_shuffle_epi8(lutx4and6, ix4);
-        let vx6 = _mm_shuffle_epi8(lutx4and6, ix6);
+        let vx3 = _mm_shuffle_epi8(self.lutx3, ix3);
+        let vx4 = _mm_shuffle_epi8(self.lutx4, ix4);
+        let vx6 = _mm_shuffle_epi8(self.lutx6, ix6);
 
         // Aggregate results
         let dec = _mm_or_si128(
@@ -83,47 +156,51 @@ pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), Deco
         // operations and constants of the algorithm, this way when
         // `_mm_shuffle_epi8` receives an out of bounds index it will
         // return 0 which is not ok
-        let ok = _mm_movemask_epi8(dec) as u32;
-        if ok != 0xffff {
-            // TODO: Error index
-            Err(InvalidCharAt(0))?
-        }
+        (dec, _mm_movemask_epi8(dec) as u32)
+    }
+}
 
-        let dec = {
-            // Pick even bytes containing most significant nibbles
-            let temp = _mm_andnot_si128(dec, m);
-            // Peform a 12 bit shift
-            let temp = _mm_sll_epi64(temp, tmpsll);
-            _mm_andnot_si128(temp, dec)
-        };
+/// Checks if the `len` chars at `src` are valid hex, the length isn't checked
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads
+pub unsafe fn validate_raw(src: *const u8, len: usize, rule: CaseRule) -> Result<(), DecodeError> {
+    use DecodeError::*;
 
-        // Takes only odd bytes
-        // Final result, must be fliped
-        let dec = _mm_andnot_si128(_mm_shuffle_epi8(dec, idec), filled);
+    let lookup = Lookup::new(rule);
 
-        // Saves the final result
-        copy_nonoverlapping(&dec as *const _ as *const u8, b, 8);
+    // Mask of the invalid chars of the 16 at `p`
+    let check = |p: *const u8| {
+        let (_, ok) = lookup.apply(_mm_loadu_si128(p as *const __m128i));
+        !ok & 0xffff
+    };
+
+    let mut i = 0;
+    while len - i >= 16 {
+        let bad = check(src.add(i));
+        if bad != 0 {
+            Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
+        }
 
-        p = p.add(16);
-        b = b.add(8);
+        i += 16;
     }
 
-    // Handle the remaining of bytes
-    while p < p_end {
-        let msn = *HEX_NIBBLE_DECODE.get_unchecked(*p as usize);
-        if msn > 0xf {
-            Err(InvalidCharAt(0))?
-        }
-        p = p.add(1);
+    if i == len {
+        return Ok(());
+    }
 
-        let lsn = *HEX_NIBBLE_DECODE.get_unchecked(*p as usize);
-        if lsn > 0xf {
-            Err(InvalidCharAt(0))?
-        }
-        p = p.add(1);
+    if len < 16 {
+        return swar::validate_raw(src, len, rule);
+    }
 
-        *b = (msn << 4) | lsn;
-        b = b.add(1);
+    // Overlaps the last full block, the chars checked again are valid
+    // so the first invalid one is still reported
+    let i = len - 16;
+    let bad = check(src.add(i));
+    if bad != 0 {
+        Err(InvalidCharAt(i + bad.trailing_zeros() as usize))?
     }
 
     Ok(())
@@ -131,23 +208,22 @@ pub unsafe fn decode_noalloc(input: &[u8], output: &mut [u8]) -> Result<(), Deco
 
 ///////////////////////////////////////////////////////////////////////////////
 
-#[inline(always)]
-pub unsafe fn encode(input: &[u8]) -> String {
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module, `src` must
+/// be valid for `len` reads and `dst` for `len * 2` writes, without overlapping
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, case: Case) {
     // Constants
-    let lut = _mm_set_epi64x(HEX_ENCODE_64LUT_1, HEX_ENCODE_64LUT_0);
+    let (lut1, lut0) = case.lut();
+    let lut = _mm_set_epi64x(lut1, lut0);
     let umask = _mm_set1_epi32(MN_MASK);
     let lmask = _mm_set1_epi32(LN_MASK);
     let srl = _mm_set1_epi64x(4);
 
-    let c = input.len();
-    let mut p = input.as_ptr() as *const i8;
-    let p_end = p.add(c);
-
-    // * NOTE: each byte need two other bytes, hence shift left 1 bits
-    let mut v = alloc(c << 1);
-    let mut b = v.as_mut_ptr() as *mut u8;
-
-    while p.offset(15) < p_end {
+    // Encodes the 16 bytes at `p` into the 32 chars at `b`
+    let block = |p: *const u8, b: *mut u8| {
         // TODO: how about _mm_lddqu_si128?
         // * NOTE: no measurable change when taking 2 u64 at the time instead of 16 u8
         // but this will required forcing the input to be 8 bytes aligned, witch is
@@ -170,32 +246,100 @@ pub unsafe fn encode(input: &[u8]) -> String {
         // ! FIXME: use copy_nonoverlapping
         _mm_storeu_si128(b as *mut _, hex0);
         _mm_storeu_si128(b.add(16) as *mut _, hex1);
+    };
 
-        p = p.add(16);
-        b = b.add(32);
+    let mut i = 0;
+    while len - i >= 16 {
+        block(src.add(i), dst.add(i << 1));
+        i += 16;
     }
 
-    // loop through the remaining 15 or less bytes
-
-    while p < p_end {
-        let j = ((*p as u8) as usize) << 1;
-        *b = *HEX_ENCODE.get_unchecked(j);
-        *b.add(1) = *HEX_ENCODE.get_unchecked(j | 1);
-
-        p = p.add(1);
-        b = b.add(2);
+    if i == len {
+        return;
     }
 
-    String::from_utf8_unchecked(v)
+    if len >= 16 {
+        // Overlaps the last full block, its chars are written again
+        let i = len - 16;
+        block(src.add(i), dst.add(i << 1));
+    } else {
+        // Shorter than a block
+        swar::encode_raw(src, len, dst, case);
+    }
 }
 
-#[inline(always)]
-pub fn meet_requirements() -> bool {
-    if is_x86_feature_detected!("sse2") && is_x86_feature_detected!("ssse3") {
-        return true;
+/// Moves every char of `input` not in `skip` to the start of `output`,
+/// returning how many were kept
+///
+/// # Safety
+///
+/// Requires the CPU to support the instruction set of this module
+pub unsafe fn compact(
+    input: &[u8],
+    skip: &SkipSet,
+    output: &mut [u8],
+) -> Result<usize, DecodeError> {
+    use DecodeError::*;
+
+    assert!(output.len() >= input.len());
+
+    // Constants
+    let lut_skip = _mm_loadu_si128(skip.lut.as_ptr() as *const __m128i);
+    let lut_hex = _mm_loadu_si128(skip::HEX_BITSET.as_ptr() as *const __m128i);
+    let bits = _mm_set_epi64x(0, 0x8040201008040201u64 as i64);
+    let x0f = _mm_set1_epi8(0x0f);
+    let x08 = 0x0808080808080808u64;
+    let zero = _mm_setzero_si128();
+
+    let c = input.len();
+    let p = input.as_ptr();
+    let b = output.as_mut_ptr();
+    let mut i = 0;
+    let mut j = 0;
+
+    while i + 16 <= c {
+        let slice = _mm_loadu_si128(p.add(i) as *const __m128i);
+
+        // Bit of the most significant nibble in the row of the least significant one,
+        // non ASCII chars have no bit so they are never in any set
+        let lo = _mm_and_si128(slice, x0f);
+        let bit = _mm_shuffle_epi8(bits, _mm_and_si128(_mm_srli_epi16(slice, 4), x0f));
+        let kept = _mm_cmpeq_epi8(_mm_and_si128(_mm_shuffle_epi8(lut_skip, lo), bit), zero);
+        let not_hex = _mm_cmpeq_epi8(_mm_and_si128(_mm_shuffle_epi8(lut_hex, lo), bit), zero);
+
+        let kept = _mm_movemask_epi8(kept) as u32;
+        let invalid = kept & _mm_movemask_epi8(not_hex) as u32;
+        if invalid != 0 {
+            Err(InvalidCharAt(i + invalid.trailing_zeros() as usize))?
+        }
+
+        if kept == 0xffff {
+            _mm_storeu_si128(b.add(j) as *mut _, slice);
+            j += 16;
+        } else {
+            // Compacts each half on its own, `output` is never shorter than
+            // `input` so writing all 8 bytes of each half is fine
+            let lo_mask = (kept & 0xff) as usize;
+            let hi_mask = (kept >> 8) as usize;
+            let shuffle = _mm_set_epi64x(
+                (skip::COMPACT[hi_mask] + x08) as i64,
+                skip::COMPACT[lo_mask] as i64,
+            );
+            let slice = _mm_shuffle_epi8(slice, shuffle);
+
+            _mm_storel_epi64(b.add(j) as *mut _, slice);
+            j += lo_mask.count_ones() as usize;
+            _mm_storel_epi64(b.add(j) as *mut _, _mm_srli_si128(slice, 8));
+            j += hi_mask.count_ones() as usize;
+        }
+
+        i += 16;
     }
 
-    return false;
+    // Handle the remaining bytes
+    let n = fallback::compact(&input[i..], skip, &mut output[j..]).map_err(|e| e.shifted(i))?;
+
+    Ok(j + n)
 }
 
-crate::tests_hex!(super::encode, super::decode, super::meet_requirements);
+crate::tests_hex!(crate::hex::Backend::Ssse3);
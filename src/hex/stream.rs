@@ -0,0 +1,248 @@
+//! Streaming hex encoding and decoding over [`Read`] and [`Write`]
+
+use std::io::{self, Read, Write};
+
+use super::{encode_noalloc, ChunkedDecoder, DecodeError};
+
+/// Number of bytes processed at once, big enough to keep the SIMD loops busy,
+/// but small under Miri so the tests can cross many chunks quickly
+const CHUNK: usize = if cfg!(miri) { 64 } else { 8192 };
+
+/// Hex encodes every byte written to it into the inner writer
+///
+/// ```rust
+/// use std::io::Write;
+///
+/// let mut encoder = bintext::hex::Encoder::new(Vec::new());
+/// encoder.write_all(b"\xa1\xf7").unwrap();
+/// encoder.write_all(b"\xd5\xe8").unwrap();
+/// assert_eq!(encoder.into_inner(), b"a1f7d5e8");
+/// ```
+#[derive(Debug)]
+pub struct Encoder<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; CHUNK << 1],
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Encoded chars are written entirely, so no byte is ever half written
+        let n = buf.len().min(CHUNK);
+        let hex = encode_noalloc(&buf[..n], &mut self.buffer).unwrap();
+        self.inner.write_all(hex.as_bytes())?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decodes the hex chars read from the inner reader
+///
+/// Decoding errors are returned as [`io::ErrorKind::InvalidData`] wrapping
+/// a [`DecodeError`], with positions counted from the start of the stream.
+///
+/// ```rust
+/// use std::io::Read;
+///
+/// let mut decoder = bintext::hex::Decoder::new(&b"a1f7d5e8"[..]);
+/// let mut v = Vec::new();
+/// decoder.read_to_end(&mut v).unwrap();
+/// assert_eq!(v, b"\xa1\xf7\xd5\xe8");
+/// ```
+#[derive(Debug)]
+pub struct Decoder<R: Read> {
+    inner: R,
+    buffer: Vec<u8>,
+    state: ChunkedDecoder,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; CHUNK],
+            state: ChunkedDecoder::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+fn invalid_data(error: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // Leaves room for the pending nibble of the last read
+        let want = (buf.len() << 1).min(CHUNK) - self.state.is_pending() as usize;
+        loop {
+            let n = self.inner.read(&mut self.buffer[..want])?;
+            if n == 0 {
+                self.state.finish().map_err(invalid_data)?;
+                return Ok(0);
+            }
+
+            // Keeps reading when only the pair of the pending nibble was read
+            let n = self
+                .state
+                .feed(&self.buffer[..n], buf)
+                .map_err(invalid_data)?;
+            if n > 0 {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::encode;
+
+    /// Reader that returns at most `step` bytes each read
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.data.len().min(buf.len()).min(self.step);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn data() -> Vec<u8> {
+        (0..(3 * CHUNK + 7)).map(|i| (i * 37) as u8).collect()
+    }
+
+    fn decode_error(e: io::Error) -> DecodeError {
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        *e.into_inner().unwrap().downcast::<DecodeError>().unwrap()
+    }
+
+    #[test]
+    fn encoding() {
+        let input = data();
+        let expected = encode(&input);
+
+        for step in [1, 3, 64, CHUNK + 1, input.len()].iter() {
+            let mut encoder = Encoder::new(Vec::new());
+            for chunk in input.chunks(*step) {
+                encoder.write_all(chunk).unwrap();
+            }
+            encoder.flush().unwrap();
+            assert_eq!(encoder.into_inner(), expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn decoding() {
+        let expected = data();
+        let input = encode(&expected);
+
+        for step in [1, 3, 7, 64, CHUNK - 1, input.len()].iter() {
+            let mut decoder = Decoder::new(Trickle {
+                data: input.as_bytes(),
+                step: *step,
+            });
+            let mut v = Vec::new();
+            decoder.read_to_end(&mut v).unwrap();
+            assert_eq!(v, expected);
+
+            // Reads of odd sizes
+            let mut decoder = Decoder::new(Trickle {
+                data: input.as_bytes(),
+                step: *step,
+            });
+            let mut v = Vec::new();
+            let mut buf = [0; 5];
+            loop {
+                let n = decoder.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                v.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn decoding_errors() {
+        use DecodeError::*;
+
+        let input = encode(&data());
+        for i in [
+            0,
+            1,
+            31,
+            32,
+            CHUNK - 1,
+            CHUNK,
+            2 * CHUNK + 1,
+            input.len() - 1,
+        ]
+        .iter()
+        {
+            for step in [1, 3, 7, CHUNK - 1].iter() {
+                let mut v = input.clone();
+                v.replace_range(*i..(*i + 1), "g");
+
+                let mut decoder = Decoder::new(Trickle {
+                    data: v.as_bytes(),
+                    step: *step,
+                });
+                let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+                assert_eq!(decode_error(e), InvalidCharAt(*i));
+            }
+        }
+
+        let mut decoder = Decoder::new(Trickle {
+            data: &input.as_bytes()[1..],
+            step: 7,
+        });
+        let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(decode_error(e), OddLength);
+    }
+}
@@ -22,6 +22,34 @@ macro_rules! is_sse_ubiquitous {
     };
 }
 
+/// Detects the CPU feature at run time, without `std` only the features
+/// enabled at compile time are considered
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! is_x86_feature_enabled {
+    ($feature:tt) => {
+        is_x86_feature_detected!($feature)
+    };
+}
+
+/// Detects the CPU feature at run time, without `std` only the features
+/// enabled at compile time are considered
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! is_x86_feature_enabled {
+    ($feature:tt) => {{
+        // Hides the constant from lints about trivial conditions, since
+        // they only hold for the current target
+        #[inline(always)]
+        fn compiled_with(feature: bool) -> bool {
+            feature
+        }
+        compiled_with(cfg!(target_feature = $feature))
+    }};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! _mm256_dbg {
@@ -0,0 +1,177 @@
+//! SIMD within a register, handles 8 bytes per step using only `u64`
+//! arithmetic so it runs on every architecture
+
+use core::ptr::{read_unaligned, write_unaligned};
+
+use super::{fallback, Case, CaseRule, DecodeError};
+
+/// Repeats `b` in every byte
+const fn splat(b: u8) -> u64 {
+    u64::from_ne_bytes([b; 8])
+}
+
+const HIGH: u64 = splat(0x80);
+const LOW: u64 = splat(0x7f);
+const NIBBLE: u64 = splat(0x0f);
+
+/// Sets the high bit of the bytes in `lo..=hi`, `x` bytes must be ASCII
+#[inline(always)]
+fn in_range(x: u64, lo: u8, hi: u8) -> u64 {
+    // Neither sum can carry to the next byte
+    let ge = x.wrapping_add(splat(0x80 - lo));
+    let gt = x.wrapping_add(splat(0x7f - hi));
+    ge & !gt & HIGH
+}
+
+/// Sets the high bit of the valid chars of `x` and of its letters
+#[inline(always)]
+fn classify(x: u64, rule: CaseRule) -> (u64, u64) {
+    let ascii = !x & HIGH;
+    let y = x & LOW;
+
+    let digit = in_range(y, b'0', b'9');
+    let alpha = match rule {
+        CaseRule::Any => in_range(y | splat(0x20), b'a', b'f'),
+        CaseRule::Lower => in_range(y, b'a', b'f'),
+        CaseRule::Upper => in_range(y, b'A', b'F'),
+    };
+
+    ((digit | alpha) & ascii, alpha)
+}
+
+/// Index of the first char not set in the `ok` mask of [`classify`]
+#[inline(always)]
+fn first_invalid(ok: u64) -> usize {
+    ((!ok & HIGH).trailing_zeros() >> 3) as usize
+}
+
+/// Decodes the 8 chars of `x` into 4 bytes, or returns the index of the
+/// first invalid char
+#[inline(always)]
+fn decode_word(x: u64, rule: CaseRule) -> Result<u32, usize> {
+    let (ok, alpha) = classify(x, rule);
+    if ok != HIGH {
+        return Err(first_invalid(ok));
+    }
+
+    // Letters are 9 after their low nibble
+    let n = (x & NIBBLE) + (alpha >> 7) * 9;
+
+    // Joins each pair of nibbles then moves the bytes together
+    let n = ((n << 4) | (n >> 8)) & 0x00ff_00ff_00ff_00ff;
+    let n = (n | (n >> 8)) & 0x0000_ffff_0000_ffff;
+    Ok((n | (n >> 16)) as u32)
+}
+
+/// Encodes the 4 bytes of `x` into 8 chars, `letters` is the distance from
+/// `'0' + 10` to the first letter
+#[inline(always)]
+fn encode_word(x: u32, letters: u64) -> u64 {
+    // Spreads the bytes to every other byte, then splits them in nibbles
+    let x = x as u64;
+    let x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    let x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    let n = ((x >> 4) | (x << 8)) & NIBBLE;
+
+    // Nibbles greater than 9 get the offset to their letter
+    let alpha = (n.wrapping_add(splat(0x80 - 10)) & HIGH) >> 7;
+    n + splat(b'0') + alpha * letters
+}
+
+/// Decodes the `len` chars at `src` into `dst`
+///
+/// # Safety
+///
+/// `len` must be even, `src` must be valid for `len` reads and `dst` for
+/// `len / 2` writes, they can only overlap when `dst` isn't after `src`
+pub unsafe fn decode_raw(
+    src: *const u8,
+    len: usize,
+    dst: *mut u8,
+    rule: CaseRule,
+) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let mut i = 0;
+    let mut j = 0;
+
+    // Both words are read before writing, so the output never reaches
+    // chars not read yet
+    while len - i >= 16 {
+        let lo = u64::from_le(read_unaligned(src.add(i) as *const u64));
+        let hi = u64::from_le(read_unaligned(src.add(i + 8) as *const u64));
+
+        let lo = decode_word(lo, rule).map_err(|k| InvalidCharAt(i + k))?;
+        let hi = decode_word(hi, rule).map_err(|k| InvalidCharAt(i + 8 + k))?;
+
+        let v = (lo as u64) | ((hi as u64) << 32);
+        write_unaligned(dst.add(j) as *mut u64, v.to_le());
+
+        i += 16;
+        j += 8;
+    }
+
+    // A single word is left at most
+    if len - i >= 8 {
+        let x = u64::from_le(read_unaligned(src.add(i) as *const u64));
+        let x = decode_word(x, rule).map_err(|k| InvalidCharAt(i + k))?;
+        write_unaligned(dst.add(j) as *mut u32, x.to_le());
+
+        i += 8;
+        j += 4;
+    }
+
+    fallback::decode_raw(src.add(i), len - i, dst.add(j), rule).map_err(|e| e.shifted(i))
+}
+
+/// Checks if the `len` chars at `src` are valid hex, the length isn't checked
+///
+/// # Safety
+///
+/// `src` must be valid for `len` reads
+pub unsafe fn validate_raw(src: *const u8, len: usize, rule: CaseRule) -> Result<(), DecodeError> {
+    use DecodeError::*;
+
+    let mut i = 0;
+    while len - i >= 8 {
+        let x = u64::from_le(read_unaligned(src.add(i) as *const u64));
+        let (ok, _) = classify(x, rule);
+        if ok != HIGH {
+            Err(InvalidCharAt(i + first_invalid(ok)))?
+        }
+
+        i += 8;
+    }
+
+    fallback::validate_raw(src.add(i), len - i, rule).map_err(|e| e.shifted(i))
+}
+
+/// Encodes the `len` bytes at `src` into `dst`
+///
+/// # Safety
+///
+/// `src` must be valid for `len` reads and `dst` for `len * 2` writes,
+/// without overlapping
+pub unsafe fn encode_raw(src: *const u8, len: usize, dst: *mut u8, case: Case) {
+    let letters = match case {
+        Case::Lower => (b'a' - b'0' - 10) as u64,
+        Case::Upper => (b'A' - b'0' - 10) as u64,
+    };
+
+    let mut i = 0;
+    while len - i >= 8 {
+        let x = u64::from_le(read_unaligned(src.add(i) as *const u64));
+        let lo = encode_word(x as u32, letters);
+        let hi = encode_word((x >> 32) as u32, letters);
+
+        let b = dst.add(i << 1);
+        write_unaligned(b as *mut u64, lo.to_le());
+        write_unaligned(b.add(8) as *mut u64, hi.to_le());
+
+        i += 8;
+    }
+
+    fallback::encode_raw(src.add(i), len - i, dst.add(i << 1), case)
+}
+
+crate::tests_hex!(crate::hex::Backend::Swar);
@@ -1,10 +1,51 @@
 #[doc(hidden)]
 #[macro_export]
 macro_rules! tests_hex {
-    ($encode:path, $decode:path, $feat:path) => {
-        #[cfg(test)]
+    ($backend:expr) => {
+        $crate::tests_hex!(@tests $backend, false);
+    };
+    // Skipped when the CPU doesn't support them, unless `BINTEXT_TEST_ALL_BACKENDS` is set
+    ($backend:expr, optional) => {
+        $crate::tests_hex!(@tests $backend, true);
+    };
+    (@tests $backend:expr, $optional:expr) => {
+        #[cfg(all(test, feature = "alloc"))]
         mod tests {
-            const SAMPLES: [(&'static [u8], &'static str); 6] = [
+            use $crate::hex::{Backend, Case, CaseRule, DecodeError, EncodeError};
+
+            const BACKEND: Backend = $backend;
+
+            /// Tells if the tests can run, panics when a required backend isn't supported
+            fn supported() -> bool {
+                if BACKEND.is_supported() {
+                    return true;
+                }
+
+                if !$optional || std::env::var_os("BINTEXT_TEST_ALL_BACKENDS").is_some() {
+                    panic!("doesn't have the required instruction set");
+                }
+                false
+            }
+
+            fn decode(input: &str, rule: CaseRule) -> Result<Vec<u8>, DecodeError> {
+                BACKEND.functions().decode(input.as_bytes(), rule)
+            }
+
+            fn decode_noalloc(input: &[u8], output: &mut [u8], rule: CaseRule) -> Result<usize, DecodeError> {
+                BACKEND.functions().decode_slice(input, output, rule)
+            }
+
+            fn encode(input: &[u8], case: Case) -> String {
+                let mut output = String::new();
+                BACKEND.functions().encode_into(&mut output, input, case);
+                output
+            }
+
+            fn encode_noalloc(input: &[u8], output: &mut [u8], case: Case) -> Result<usize, EncodeError> {
+                BACKEND.functions().encode_slice(input, output, case)
+            }
+
+            const SAMPLES: [(&[u8], &str); 6] = [
                     (b"\xAd\x87\x7F", "ad877f"), // 3 bytes
                     (b"\x34\xcD\x6f\x62\xAf\xa9\x1a\x82\xC7\x24", "34cd6f62afa91a82c724"), // 10 bytes
                     (b"\x0a\x86\x16\x81\x45\x16\x51\xb7\x97\x4e\x81\x7f\xc7\xe8\x9e\xee\xbe\x61\x45\xe7",
@@ -33,34 +74,230 @@ macro_rules! tests_hex {
 
 
             #[test]
-            #[allow(unused_unsafe)]
             fn encoding() {
-                if !$feat() {
-                    panic!("doesn't have the required instruction set");
+                if !supported() {
+                    return;
                 }
 
                 for (input, expected) in SAMPLES.iter() {
-                    let r = unsafe { $encode(input) };
+                    let r = encode(input, Case::Lower);
                     assert_eq!(r, *expected);
+
+                    let r = encode(input, Case::Upper);
+                    assert_eq!(r, expected.to_uppercase());
+                }
+
+                // Every byte value in every position of the SIMD loops and tails,
+                // Miri is too slow to check them all
+                for len in (0..100).step_by(if cfg!(miri) { 13 } else { 1 }) {
+                    let input: Vec<u8> = (0..len).map(|i| (i * 101 + len * 7) as u8).collect();
+                    let r = encode(&input, Case::Lower);
+                    let expected: String = input.iter().map(|b| format!("{:02x}", b)).collect();
+                    assert_eq!(r, expected);
+
+                    let r = encode(&input, Case::Upper);
+                    let expected: String = input.iter().map(|b| format!("{:02X}", b)).collect();
+                    assert_eq!(r, expected);
+                }
+            }
+
+            #[test]
+            fn encoding_noalloc() {
+                use $crate::hex::EncodeError::*;
+
+                if !supported() {
+                    return;
+                }
+
+                for (input, expected) in SAMPLES.iter() {
+                    let n = expected.len();
+
+                    let mut v = vec![0; n - 1];
+                    let r = encode_noalloc(input, &mut v[..], Case::Lower);
+                    assert_eq!(r.unwrap_err(), OutputTooSmall { needed: n, got: n - 1 });
+
+                    for case in [Case::Lower, Case::Upper].iter() {
+                        let mut v = vec![0xcc; n + 5];
+                        let r = encode_noalloc(input, &mut v[..], *case);
+                        assert_eq!(r.unwrap(), n);
+                        assert_eq!(&v[..n], encode(input, *case).as_bytes());
+                        assert_eq!(&v[n..], &[0xcc; 5]);
+                    }
                 }
             }
 
             #[test]
-            #[allow(unused_unsafe)]
             fn decoding() {
-                if !$feat() {
-                    panic!("doesn't have the required instruction set");
+                if !supported() {
+                    return;
                 }
 
                 for (expected, input) in SAMPLES.iter() {
-                    let r = unsafe { $decode(input) };
+                    let r = decode(input, CaseRule::Any);
                     assert_eq!(r.unwrap(), *expected);
                 }
 
                 for (expected, input) in SAMPLES.iter() {
-                    let r = unsafe { $decode(&str::to_uppercase(input)) };
+                    let r = decode(&str::to_uppercase(input), CaseRule::Any);
                     assert_eq!(r.unwrap(), *expected);
                 }
+
+                // Every length of the SIMD loops and their tails
+                for len in (0..100).step_by(if cfg!(miri) { 13 } else { 1 }) {
+                    let expected: Vec<u8> = (0..len).map(|i| (i * 101 + len * 7) as u8).collect();
+                    let input: String = expected.iter().map(|b| format!("{:02x}", b)).collect();
+                    let r = decode(&input, CaseRule::Any);
+                    assert_eq!(r.unwrap(), expected);
+                }
+            }
+
+            #[test]
+            fn decoding_case() {
+                use $crate::hex::DecodeError::*;
+
+                if !supported() {
+                    return;
+                }
+
+                for (expected, input) in SAMPLES.iter() {
+                    let upper = input.to_uppercase();
+                    for (v, rule) in [
+                        (input.to_string(), CaseRule::Lower),
+                        (upper.clone(), CaseRule::Upper),
+                    ]
+                    .iter()
+                    {
+                        let r = decode(v, *rule);
+                        assert_eq!(r.unwrap(), *expected);
+                    }
+
+                    // Letters past 'f' are rejected by every rule
+                    for i in 0..input.len() {
+                        for c in ["g", "q", "v", "G", "Q", "V"].iter() {
+                            for (v, rule) in [
+                                (input.to_string(), CaseRule::Any),
+                                (input.to_string(), CaseRule::Lower),
+                                (upper.clone(), CaseRule::Upper),
+                            ]
+                            .iter()
+                            {
+                                let mut v = v.clone();
+                                v.replace_range(i..i + 1, c);
+                                let r = decode(&v, *rule);
+                                assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?} {:?}", v, rule);
+                            }
+                        }
+                    }
+
+                    // Wrong case letters are reported at every position
+                    for i in 0..input.len() {
+                        if !input.as_bytes()[i].is_ascii_alphabetic() {
+                            continue;
+                        }
+
+                        for (v, other, rule) in [
+                            (input.to_string(), &upper, CaseRule::Lower),
+                            (upper.clone(), &input.to_string(), CaseRule::Upper),
+                        ]
+                        .iter()
+                        {
+                            let mut v = v.clone();
+                            v.replace_range(i..i + 1, &other[i..i + 1]);
+                            let r = decode(&v, CaseRule::Any);
+                            assert_eq!(r.unwrap(), *expected);
+                            let r = decode(&v, *rule);
+                            assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", v);
+                        }
+                    }
+                }
+            }
+
+            #[test]
+            fn decoding_errors() {
+                use $crate::hex::DecodeError::*;
+
+                if !supported() {
+                    return;
+                }
+
+                // Every backend must agree with the fallback one
+                let fallback = $crate::hex::Backend::Fallback.functions();
+                let step = if cfg!(miri) { 7 } else { 1 };
+                let values = if cfg!(miri) { 17 } else { 1 };
+
+                for (expected, input) in SAMPLES.iter() {
+                    let mut v = vec![0; expected.len()];
+                    let mut w = vec![0; expected.len()];
+                    for i in (0..input.len()).step_by(step) {
+                        for c in (0..=255u8).step_by(values) {
+                            let mut b = input.as_bytes().to_vec();
+                            b[i] = c;
+                            let r = decode_noalloc(&b, &mut v[..], CaseRule::Any);
+                            let f = fallback.decode_slice(&b, &mut w[..], CaseRule::Any);
+                            assert_eq!(r, f, "{:?}", b);
+                            if r.is_ok() {
+                                assert_eq!(v, w, "{:?}", b);
+                            }
+                            if !c.is_ascii_hexdigit() {
+                                assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", b);
+                            }
+
+                            // Only the first invalid char is reported
+                            b[input.len() - 1] = c;
+                            let r = decode_noalloc(&b, &mut v[..], CaseRule::Any);
+                            let f = fallback.decode_slice(&b, &mut w[..], CaseRule::Any);
+                            assert_eq!(r, f, "{:?}", b);
+                            if r.is_ok() {
+                                assert_eq!(v, w, "{:?}", b);
+                            }
+                        }
+
+                        // Non ascii chars
+                        if i + 2 <= input.len() {
+                            let mut v = input.to_string();
+                            v.replace_range(i..i + 2, "é");
+                            let r = decode(&v, CaseRule::Any);
+                            assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", v);
+                        }
+                    }
+                }
+            }
+
+            #[test]
+            fn decoding_noalloc() {
+                use $crate::hex::DecodeError::*;
+
+                if !supported() {
+                    return;
+                }
+
+                for (expected, input) in SAMPLES.iter() {
+                    let n = expected.len();
+
+                    let mut v = vec![0; n - 1];
+                    let r = decode_noalloc(input.as_bytes(), &mut v[..], CaseRule::Any);
+                    assert_eq!(r.unwrap_err(), OutputTooSmall { needed: n, got: n - 1 });
+
+                    let mut v = vec![0xcc; n + 5];
+                    let r = decode_noalloc(input.as_bytes(), &mut v[..], CaseRule::Any);
+                    assert_eq!(r.unwrap(), n);
+                    assert_eq!(&v[..n], *expected);
+                    assert_eq!(&v[n..], &[0xcc; 5]);
+
+                    let r = decode_noalloc(&input.as_bytes()[1..], &mut v[..], CaseRule::Any);
+                    assert_eq!(r.unwrap_err(), OddLength);
+
+                    // Stray non ascii bytes, that can't be in a `str` alone
+                    let step = if cfg!(miri) { 7 } else { 1 };
+                    for i in (0..input.len()).step_by(step) {
+                        for c in [0x80, 0x89, 0xb0, 0xc6, 0xe6, 0xff].iter() {
+                            let mut b = input.as_bytes().to_vec();
+                            b[i] = *c;
+                            let r = decode_noalloc(&b, &mut v[..], CaseRule::Any);
+                            assert_eq!(r.unwrap_err(), InvalidCharAt(i), "{:?}", b);
+                        }
+                    }
+                }
             }
         }
     }
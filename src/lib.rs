@@ -1,5 +1,5 @@
 //! Binary text encoding and decoding with support for SIMD (AVX2 and
-//! SSSE3) with good fallback performance.
+//! SSSE3) with good fallback performance. Supports [`hex`](mod@hex) and [`base64`].
 //!
 //! The main idea of this crate is to have a zero copy binary deserialization
 //! for text formats.
@@ -11,22 +11,79 @@
 //! of offset need to move the bytes is less than `N` thus by providing an start padding
 //! in the binary encoded text of `N - 1` it's possible to align the data up to `N`.
 //!
-//! **Quick note** this crate will only accept padding equal or grater than `N`, because
-//! it's a bit cheap to do this way.
+//! **Quick note** this crate will only accept padding equal or grater than `N` by default,
+//! because it's a bit cheap to do this way, [`hex::decode_aligned_tight`] accepts any padding
+//! shifting the decoded bytes forward when needed.
 //!
 //! ```rust
+//! # #[cfg(feature = "alloc")] {
 //! // Padding of 8 (suppose it was read form a file)
-//! let mut hex = "--------a1f7d5e8d14f0f76".to_string();
-//!
-//! unsafe {
-//!     // Decode with padding of 8 and alignment of 8
-//!     let slice = bintext::hex::decode_aligned(&mut hex, 8, 8).unwrap();
-//!     // Data is aligned so you can safely do this:
-//!     let slice: &[u64] = std::slice::from_raw_parts(
-//!        slice.as_ptr() as *const _,
-//!        slice.len() / std::mem::size_of::<u64>()
-//!     );
-//! }
+//! let hex = "--------a1f7d5e8d14f0f76".to_string();
+//!
+//! // Decode with padding of 8 and alignment of 8
+//! let buf = bintext::hex::AlignedHexBuf::new(hex, 8, 8).unwrap();
+//! // Data is aligned so it can be viewed as `u64`
+//! let slice: &[u64] = buf.as_u64().unwrap();
+//! # }
 //! ```
+//!
+//! Use [`hex::decode_aligned`] to decode directly in a `&mut str` owned by someone else,
+//! and [`hex::encode_aligned`] to write the padding in front of the encoded data.
+//!
+//! Both codecs implement [`Engine`], configured through [`hex::Hex`] and [`base64::Config`].
+//! Hex literals are decoded at compile time by [`hex!`] and [`aligned_hex!`].
+//!
+//! ### Features
+//!
+//! - `std` (default) detects the CPU features at run time and adds the streaming
+//!   [`hex::Encoder`] and [`hex::Decoder`], implies `alloc`
+//! - `alloc` adds the functions returning `Vec` and `String`
+//!
+//! Without both only the functions writing into given buffers are left, like
+//! [`hex::decode_noalloc`], [`hex::decode_aligned`] and [`hex::encode_noalloc`],
+//! and the SIMD backends are picked by the target features enabled at compile time.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod base64;
+mod dispatch;
+mod engine;
 pub mod hex;
+
+pub use dispatch::{Backend, UnsupportedBackend, BACKEND_VAR};
+pub use engine::Engine;
+
+/// Plain old data types, that can be safely viewed from aligned decoded bytes
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type, it also can't have
+/// any padding bytes or be zero sized. Zero sized types, like `[u8; 0]`, fail
+/// to build where they're viewed from the decoded bytes.
+///
+/// ```compile_fail
+/// let mut bytes = *b"00";
+/// let hex = core::str::from_utf8_mut(&mut bytes).unwrap();
+/// let _ = unsafe { bintext::hex::decode_aligned_as::<[u8; 0]>(hex, 0) };
+/// ```
+pub unsafe trait Pod: Copy + 'static {
+    /// Referenced before viewing the bytes, so the build fails for zero sized types
+    #[doc(hidden)]
+    const NOT_ZERO_SIZED: () = assert!(
+        core::mem::size_of::<Self>() > 0,
+        "Pod types can't be zero sized"
+    );
+}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}